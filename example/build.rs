@@ -0,0 +1,3 @@
+fn main() {
+    instability::emit_build_cfgs!();
+}
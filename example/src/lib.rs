@@ -55,6 +55,121 @@ pub fn unstable_function() {
     unimplemented!()
 }
 
+/// A function that is only available with an optional feature
+///
+/// This function is stable, but requires the `extra` feature.
+#[instability::gated(feature = "extra")]
+pub fn gated_function() {
+    unimplemented!()
+}
+
+/// A legacy function
+///
+/// This function has been removed, but is kept available behind a feature.
+#[instability::legacy(
+    feature = "old-function",
+    removed_in = "v1.2.0",
+    note = "Use `stable_function` instead."
+)]
+pub fn old_function() {
+    unimplemented!()
+}
+
+/// A function from the original API
+///
+/// This function is only available when the `api-v1` feature selects the first API level.
+#[instability::api(removed = 2)]
+pub fn api_function() -> u8 {
+    1
+}
+
+/// A function from the current API
+///
+/// This function replaces the one from the first API level, and is available by default.
+#[instability::api(added = 2)]
+pub fn api_function() -> u16 {
+    2
+}
+
+/// A function that requires a newer version of Rust
+///
+/// This function is stable, but requires Rust 1.90 or newer.
+#[instability::requires_rust("1.90")]
+pub fn newer_rust_function() {
+    unimplemented!()
+}
+
+/// An unstable function that requires a nightly compiler
+///
+/// This function is unstable, and left out on stable compilers even with its feature enabled.
+#[instability::unstable(feature = "nightly-function", nightly)]
+pub fn unstable_nightly_function() {
+    unimplemented!()
+}
+
+/// A stable function that behaves differently when an unstable feature is enabled
+///
+/// This function is stable, but its result depends on the `unstable-function` feature.
+#[instability::stable(since = "v1.0.0")]
+pub fn stable_function_with_unstable_behavior() -> &'static str {
+    instability::if_unstable!(feature = "function"; {
+        "unstable"
+    } else {
+        "stable"
+    })
+}
+
+instability::unstable_items! {
+    feature = "functions", issue = "#2";
+
+    /// An unstable function from a group of unstable functions
+    ///
+    /// This function is unstable.
+    pub fn first_unstable_function() {
+        unimplemented!()
+    }
+
+    /// Another unstable function from a group of unstable functions
+    ///
+    /// This function is unstable.
+    pub fn second_unstable_function() {
+        unimplemented!()
+    }
+}
+
+/// An unstable function that requires two features
+///
+/// This function is unstable and only available when both of its features are enabled.
+#[instability::unstable(feature = "function")]
+#[instability::unstable(feature = "stacked-function", issue = "#1")]
+pub fn unstable_stacked_function() {
+    unimplemented!()
+}
+
+/// An unstable function that is not documented unless its feature is enabled
+///
+/// This function is unstable and hidden from docs.
+#[instability::unstable(feature = "undocumented-function", docs = false)]
+pub fn unstable_undocumented_function() {
+    unimplemented!()
+}
+
+/// An unstable function exported with an unmangled symbol
+///
+/// This function is unstable, and its symbol is only exported when the feature is enabled.
+#[instability::unstable(feature = "function")]
+#[no_mangle]
+pub extern "C" fn instability_example_unstable_function() {}
+
+/// A stable function that is unstable to use in `const` contexts
+///
+/// This function is stable, but it is only a `const fn` when the feature is enabled.
+#[instability::stable(since = "v1.0.0")]
+#[instability::unstable_const(feature = "function")]
+pub fn unstable_const_function() -> u8 {
+    42
+}
+
 /// A stable struct
 ///
 /// This struct is stable
@@ -81,6 +196,16 @@ impl StableStruct {
     }
 }
 
+#[instability::stable(since = "v1.1.0")]
+impl StableStruct {
+    /// A stable method in a stable impl block
+    ///
+    /// This method is stable.
+    pub fn stable_impl_method(&self) {
+        unimplemented!()
+    }
+}
+
 /// An unstable struct
 ///
 /// This struct is unstable
@@ -111,6 +236,33 @@ impl UnstableStruct {
     }
 }
 
+#[instability::unstable(feature = "method")]
+impl UnstableStruct {
+    /// An unstable method in an unstable impl block
+    ///
+    /// This method is unstable.
+    pub fn unstable_impl_method(&self) {
+        unimplemented!()
+    }
+
+    /// An unstable constant in an unstable impl block
+    ///
+    /// This constant is unstable.
+    pub const UNSTABLE_IMPL_CONSTANT: u8 = 42;
+
+    /// A stable method in an unstable impl block
+    ///
+    /// This method keeps its own stability.
+    #[allow(
+        unreachable_pub,
+        // reason = "The unstable macros cannot make the method pub(crate)"
+    )]
+    #[instability::stable(since = "v1.1.0")]
+    pub fn stable_impl_method_in_unstable_impl(&self) {
+        unimplemented!()
+    }
+}
+
 /// An unstable struct with an issue link
 ///
 /// This struct is unstable and has an issue link.
@@ -119,6 +271,65 @@ pub struct UnstableStructWithIssue {
     pub x: u8,
 }
 
+/// An unstable struct with a reason and a note
+///
+/// This struct is unstable and documents why.
+#[instability::unstable(
+    feature = "struct-with-reason",
+    since = "0.3.0",
+    reason = "naming not final",
+    note = "Consider using `UnstableStruct` until this is settled."
+)]
+pub struct UnstableStructWithReason {
+    pub x: u8,
+}
+
+/// A stable union
+///
+/// This union is stable.
+#[instability::stable(since = "v1.0.0")]
+pub union StableUnion {
+    pub x: u8,
+    pub y: i8,
+}
+
+/// An unstable union
+///
+/// This union is unstable.
+#[instability::unstable(feature = "union")]
+pub union UnstableUnion {
+    pub x: u8,
+    pub y: i8,
+}
+
+/// A stable struct with unstable derived trait implementations
+///
+/// This struct is stable, but its `Hash` and `PartialEq` implementations are unstable.
+#[instability::stable(since = "v1.0.0")]
+#[instability::unstable_derive(feature = "derive", Hash, PartialEq)]
+pub struct StableStructWithUnstableDerives {
+    pub x: u8,
+}
+
+/// A stable struct with an unstable generic parameter
+///
+/// This struct is stable, but its `U` parameter can only be specified when the feature is enabled.
+#[instability::stable(since = "v1.0.0")]
+#[instability::unstable_param(U, feature = "generic-param")]
+#[derive(Debug)]
+pub struct StableStructWithUnstableParam<T, U = u8> {
+    pub x: T,
+    pub y: U,
+    z: u8,
+}
+
+impl<T> StableStructWithUnstableParam<T> {
+    /// Create a struct with the default `U` parameter
+    pub fn new(x: T) -> Self {
+        Self { x, y: 0, z: 0 }
+    }
+}
+
 /// A stable trait
 ///
 /// This trait is stable
@@ -193,6 +404,95 @@ pub enum UnstableEnum {
     // UnstableVariant,
 }
 
+/// A stable macro
+///
+/// This macro is stable.
+#[instability::stable(since = "v1.0.0")]
+#[macro_export]
+macro_rules! stable_macro {
+    () => {};
+}
+
+/// An unstable macro
+///
+/// This macro is unstable.
+#[instability::unstable(feature = "macro")]
+#[macro_export]
+macro_rules! unstable_macro {
+    () => {};
+}
+
+/// A module with an unstable macro
+pub mod macros {
+    /// An unstable macro defined in a module
+    ///
+    /// This macro is unstable, and can be used by path within the crate with or without its
+    /// feature.
+    #[instability::unstable(feature = "macro")]
+    #[macro_export]
+    macro_rules! unstable_module_macro {
+        () => {};
+    }
+
+    /// A function that uses the unstable macro by path
+    pub fn use_unstable_macro() {
+        crate::macros::unstable_module_macro!();
+    }
+}
+
+/// A macro that declares a newtype, standing in for item-producing macros such as `bitflags!`
+macro_rules! newtype {
+    ($(#[$meta:meta])* $vis:vis struct $name:ident($inner:ty);) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, Copy, PartialEq)]
+        $vis struct $name(pub $inner);
+    };
+}
+
+#[instability::stable(since = "v1.1.0")]
+newtype! {
+    /// A stable newtype declared by an annotated macro invocation
+    ///
+    /// This newtype is stable.
+    pub struct StableMacroNewtype(u8);
+}
+
+#[instability::unstable(feature = "newtype", fallback_vis = "pub(crate)")]
+newtype! {
+    /// An unstable newtype declared by an annotated macro invocation
+    ///
+    /// This newtype is unstable, and is still available within the crate without its feature.
+    pub struct UnstableMacroNewtype(u8);
+}
+
+/// A function that uses the unstable newtype within the crate
+fn use_unstable_newtype() -> UnstableMacroNewtype {
+    UnstableMacroNewtype(42)
+}
+
+extern "C" {
+    /// A stable foreign function
+    ///
+    /// This function is stable.
+    #[instability::stable(since = "v1.0.0")]
+    pub fn abs(input: i32) -> i32;
+
+    /// An unstable foreign function
+    ///
+    /// This function is unstable.
+    #[instability::unstable(feature = "foreign-function")]
+    pub fn labs(input: core::ffi::c_long) -> core::ffi::c_long;
+}
+
+// Unstable foreign functions
+#[instability::unstable(feature = "foreign-function")]
+extern "C" {
+    /// An unstable foreign function
+    ///
+    /// This function is unstable.
+    pub fn llabs(input: core::ffi::c_longlong) -> core::ffi::c_longlong;
+}
+
 /// A stable module
 ///
 /// This module is stable.
@@ -236,6 +536,27 @@ pub mod unstable {
     }
 }
 
+/// An unstable module with unstable contents
+///
+/// This module and each of its public items are unstable.
+#[instability::unstable(feature = "recursive-module", recursive)]
+pub mod unstable_recursive {
+    /// An unstable function
+    ///
+    /// This function is unstable because its module is.
+    pub fn unstable_function() {
+        unimplemented!()
+    }
+
+    /// A stable function
+    ///
+    /// This function is stable.
+    #[instability::stable(since = "v1.0.0")]
+    pub fn stable_function() {
+        unimplemented!()
+    }
+}
+
 /// A private module
 ///
 /// This module is private.
@@ -267,6 +588,18 @@ pub use private::private_function as stable_reexport;
 #[instability::unstable(feature = "reexport")]
 pub use private::private_function as unstable_reexport;
 
+/// A stable re-export of a private stable item, documented inline
+///
+/// This re-export is stable and carries its own stability information.
+#[instability::stable(since = "v1.0.0", inline)]
+pub use private::private_function as stable_inline_reexport;
+
+/// An unstable re-export of a private stable item, documented inline
+///
+/// This re-export is unstable and carries its own stability information.
+#[instability::unstable(feature = "reexport", inline)]
+pub use private::private_function as unstable_inline_reexport;
+
 // This does not work as the unstable_private_function is only public within the crate and cannot
 // be re-exported
 // /// A stable reexport of a private unstable item
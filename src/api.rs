@@ -0,0 +1,147 @@
+use darling::{ast::NestedMeta, Error, FromMeta};
+use indoc::formatdoc;
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{parse_quote, Item};
+
+use crate::gated::expand_gated;
+
+pub fn api_macro(args: TokenStream, input: TokenStream) -> TokenStream {
+    let attributes = match NestedMeta::parse_meta_list(args) {
+        Ok(attributes) => attributes,
+        Err(err) => return Error::from(err).write_errors(),
+    };
+    let api_attribute = match ApiAttribute::from_list(&attributes) {
+        Ok(attributes) => attributes,
+        Err(err) => return err.write_errors(),
+    };
+    match syn::parse2::<Item>(input) {
+        Ok(item) => api_attribute.expand_item(item),
+        Err(err) => Error::from(err).write_errors(),
+    }
+}
+
+#[derive(Debug, Default, FromMeta)]
+#[darling(and_then = Self::validate)]
+pub struct ApiAttribute {
+    /// The API level in which the item was added.
+    added: Option<u32>,
+
+    /// The API level in which the item was removed.
+    removed: Option<u32>,
+}
+
+impl ApiAttribute {
+    fn validate(self) -> darling::Result<Self> {
+        match (self.added, self.removed) {
+            (None, None) => Err(Error::custom("expected `added`, `removed` or both")),
+            (Some(0), _) | (_, Some(0)) => Err(Error::custom("API levels start at 1")),
+            (_, Some(1)) => Err(Error::custom("`removed` must be at least API level 2")),
+            (Some(added), Some(removed)) if added >= removed => Err(Error::custom(
+                "`removed` must be a later API level than `added`",
+            )),
+            _ => Ok(self),
+        }
+    }
+
+    /// Gate the item on the selected API level.
+    ///
+    /// The API level is selected by enabling one of the `api-v<level>` crate features, and is the
+    /// latest level if none of them is enabled. Items are available from the `added` level, and up
+    /// to but excluding the `removed` level. Enabling more than one of the features is rejected by
+    /// the build script cfgs instead, so that the check is done once rather than for every item.
+    pub fn expand_item(&self, item: Item) -> TokenStream {
+        let mut predicates = Vec::new();
+        if let Some(added) = self.added {
+            let earlier = (1..added).map(feature_flag);
+            predicates.push(quote! { not(any(#(feature = #earlier),*)) });
+        }
+        if let Some(removed) = self.removed {
+            let earlier = (1..removed).map(feature_flag);
+            predicates.push(quote! { any(#(feature = #earlier),*) });
+        }
+        let predicate = quote! { all(#(#predicates),*) };
+        expand_gated(item, &predicate, TokenStream::new(), vec![self.doc_attr()])
+    }
+
+    fn doc_attr(&self) -> syn::Attribute {
+        let first = self.added.unwrap_or(1);
+        let levels = match self.removed.map(|removed| removed - 1) {
+            None => format!("API level {first} and later"),
+            Some(last) if last == first => format!("API level {first}"),
+            Some(last) => format!("API levels {first} to {last}"),
+        };
+        let doc = formatdoc! {"
+            # API level
+
+            This API is only available in {levels}.
+
+            The API level is selected with the `api-v<level>` crate features, and is the latest
+            level if none of them is enabled."};
+        parse_quote! { #[doc = #doc] }
+    }
+}
+
+fn feature_flag(level: u32) -> String {
+    format!("api-v{level}")
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use quote::quote;
+    use syn::parse_quote;
+
+    use super::*;
+
+    #[test]
+    fn expand_added() {
+        let item: Item = parse_quote! {
+            pub fn foo() {}
+        };
+        let api = ApiAttribute {
+            added: Some(2),
+            ..Default::default()
+        };
+        let tokens = api.expand_item(item);
+        let doc = "# API level\n\nThis API is only available in API level 2 and later.\n\nThe API level is selected with the `api-v<level>` crate features, and is the latest\nlevel if none of them is enabled.";
+        let expected = quote! {
+            #[cfg(all(not(any(feature = "api-v1"))))]
+            #[doc = #doc]
+            pub fn foo() {}
+        };
+        assert_eq!(tokens.to_string(), expected.to_string());
+    }
+
+    #[test]
+    fn expand_removed() {
+        let item: Item = parse_quote! {
+            pub fn foo() {}
+        };
+        let api = ApiAttribute {
+            removed: Some(2),
+            ..Default::default()
+        };
+        let tokens = api.expand_item(item);
+        let doc = "# API level\n\nThis API is only available in API level 1.\n\nThe API level is selected with the `api-v<level>` crate features, and is the latest\nlevel if none of them is enabled.";
+        let expected = quote! {
+            #[cfg(all(any(feature = "api-v1")))]
+            #[doc = #doc]
+            pub fn foo() {}
+        };
+        assert_eq!(tokens.to_string(), expected.to_string());
+    }
+
+    #[test]
+    fn invalid_levels() {
+        for args in [
+            quote! {},
+            quote! { added = 0 },
+            quote! { removed = 1 },
+            quote! { added = 2, removed = 2 },
+        ] {
+            let attributes = NestedMeta::parse_meta_list(args).unwrap();
+            assert!(ApiAttribute::from_list(&attributes).is_err());
+        }
+    }
+}
@@ -0,0 +1,63 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+
+/// The body of a build script that emits the cfgs used by this crate's attributes.
+///
+/// This emits an `instability_rust = "1.<minor>"` cfg for each Rust version up to and including
+/// the version of the compiler, so that `requires_rust` can gate items on the compiler version, and
+/// an `instability_nightly` cfg on nightly compilers for `unstable(nightly)` items. It also
+/// declares the `instability_disable_unstable_docs` cfg that unstable items are gated on, and fails
+/// the build when more than one `api-v<level>` feature is enabled, except on docs.rs.
+pub fn emit_build_cfgs_macro(input: TokenStream) -> TokenStream {
+    if !input.is_empty() {
+        return syn::Error::new_spanned(input, "expected no arguments").to_compile_error();
+    }
+    quote! {
+        {
+            let rustc = ::std::env::var_os("RUSTC").unwrap_or_else(|| "rustc".into());
+            let output = ::std::process::Command::new(rustc)
+                .arg("--version")
+                .output()
+                .expect("failed to run `rustc --version`");
+            let version = ::std::string::String::from_utf8_lossy(&output.stdout);
+            // e.g. `rustc 1.85.0 (4d91de4e4 2025-02-17)`
+            let minor = version
+                .split_whitespace()
+                .nth(1)
+                .and_then(|version| version.split('.').nth(1))
+                .and_then(|minor| minor.parse::<u64>().ok())
+                .expect("failed to parse the output of `rustc --version`");
+            ::std::println!("cargo::rustc-check-cfg=cfg(instability_rust, values(any()))");
+            for minor in 0..=minor {
+                ::std::println!("cargo::rustc-cfg=instability_rust=\"1.{}\"", minor);
+            }
+            ::std::println!("cargo::rustc-check-cfg=cfg(instability_nightly)");
+            ::std::println!("cargo::rustc-check-cfg=cfg(instability_disable_unstable_docs)");
+            if version.contains("-nightly") || version.contains("-dev") {
+                ::std::println!("cargo::rustc-cfg=instability_nightly");
+            }
+
+            // Only one API level can be selected at a time, but docs.rs enables all features.
+            let docs = ::std::env::var_os("DOCS_RS").is_some()
+                || ::std::env::var_os("CARGO_CFG_DOCSRS").is_some();
+            let mut api_levels = ::std::env::vars()
+                .filter_map(|(name, _)| {
+                    name.strip_prefix("CARGO_FEATURE_API_V")
+                        .and_then(|level| level.parse::<u32>().ok())
+                })
+                .collect::<::std::vec::Vec<_>>();
+            api_levels.sort_unstable();
+            if !docs && api_levels.len() > 1 {
+                let features = api_levels
+                    .iter()
+                    .map(|level| ::std::format!("`api-v{}`", level))
+                    .collect::<::std::vec::Vec<_>>()
+                    .join(", ");
+                ::std::println!(
+                    "cargo::error=the `api-v<level>` crate features are mutually exclusive, but {} are enabled",
+                    features
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,80 @@
+use darling::{Error, FromMeta};
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+
+/// A configuration option that can be switched on or off, or set to a custom cfg name.
+///
+/// This parses `name`, `name = true`, `name = false` and `name = "cfg_name"` from the attribute
+/// arguments.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CfgFlag {
+    Enabled,
+    Disabled,
+    Cfg(syn::Ident),
+}
+
+impl FromMeta for CfgFlag {
+    fn from_word() -> darling::Result<Self> {
+        Ok(Self::Enabled)
+    }
+
+    fn from_bool(value: bool) -> darling::Result<Self> {
+        Ok(if value { Self::Enabled } else { Self::Disabled })
+    }
+
+    fn from_string(value: &str) -> darling::Result<Self> {
+        syn::parse_str(value)
+            .map(Self::Cfg)
+            .map_err(|_| Error::custom(format!("`{value}` is not a valid cfg name")))
+    }
+}
+
+impl CfgFlag {
+    /// The cfg predicate under which an item is included in generated documentation.
+    pub fn docs_predicate(&self) -> Option<TokenStream> {
+        match self {
+            Self::Enabled => Some(quote! { doc }),
+            Self::Disabled => None,
+            Self::Cfg(name) => Some(quote! { all(doc, #name) }),
+        }
+    }
+
+    /// The cfg predicate under which a gated item is available, given the predicate for its
+    /// features.
+    pub fn gate_predicate(&self, features: TokenStream) -> TokenStream {
+        match self.docs_predicate() {
+            Some(docs) => quote! { any(#docs, #features) },
+            None => features,
+        }
+    }
+
+    /// The cfg predicate under which a gated item is available, given the `docs` option and the
+    /// predicate for its features.
+    ///
+    /// When `docs` is not specified, the item is included in generated documentation unless the
+    /// `instability_disable_unstable_docs` cfg is set. This is evaluated in the crate that uses
+    /// the attribute rather than when this crate is compiled, so it can be set for each crate.
+    pub fn gate_predicate_or_default(docs: Option<&Self>, features: TokenStream) -> TokenStream {
+        match docs {
+            Some(docs) => docs.gate_predicate(features),
+            None => quote! { any(all(doc, not(instability_disable_unstable_docs)), #features) },
+        }
+    }
+
+    /// The attribute that shows the required features in the documentation, if any.
+    pub fn doc_cfg_attr(&self, features: &TokenStream) -> TokenStream {
+        match self.doc_cfg_name() {
+            Some(name) => quote! { #[cfg_attr(#name, doc(cfg(#features)))] },
+            None => TokenStream::new(),
+        }
+    }
+
+    /// The cfg under which `doc(cfg(...))` attributes are emitted, defaulting to `docsrs`.
+    pub fn doc_cfg_name(&self) -> Option<syn::Ident> {
+        match self {
+            Self::Enabled => Some(format_ident!("docsrs")),
+            Self::Disabled => None,
+            Self::Cfg(name) => Some(name.clone()),
+        }
+    }
+}
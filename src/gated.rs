@@ -0,0 +1,199 @@
+use darling::{ast::NestedMeta, Error, FromMeta};
+use indoc::formatdoc;
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{parse_quote, Item};
+
+use crate::{cfg::CfgFlag, item_like::item_attrs_mut};
+
+pub fn gated_macro(args: TokenStream, input: TokenStream) -> TokenStream {
+    let attributes = match NestedMeta::parse_meta_list(args) {
+        Ok(attributes) => attributes,
+        Err(err) => return Error::from(err).write_errors(),
+    };
+    let gated_attribute = match GatedAttribute::from_list(&attributes) {
+        Ok(attributes) => attributes,
+        Err(err) => return err.write_errors(),
+    };
+    match syn::parse2::<Item>(input) {
+        Ok(item) => gated_attribute.expand_item(item),
+        Err(err) => Error::from(err).write_errors(),
+    }
+}
+
+#[derive(Debug, Default, FromMeta)]
+pub struct GatedAttribute {
+    /// The name of the crate feature that enables the item.
+    ///
+    /// Unlike the `unstable` attribute, this is used as is, without a prefix.
+    feature: String,
+
+    /// Whether the item is included when generating documentation, even if the feature is not
+    /// enabled.
+    ///
+    /// This can be `true`, `false`, or the name of a cfg that must also be set when generating
+    /// documentation. Defaults to `false`, as gated items often depend on optional dependencies
+    /// that are only available with the feature.
+    docs: Option<CfgFlag>,
+
+    /// The cfg under which a `doc(cfg(...))` attribute is added to the item.
+    ///
+    /// This can be `true` (the default, using `docsrs`), `false` to not add the attribute, or the
+    /// name of a custom cfg.
+    doc_cfg: Option<CfgFlag>,
+}
+
+impl GatedAttribute {
+    /// Gate the item on the feature, and document the feature it requires.
+    ///
+    /// Unlike unstable items, gated items have no `pub(crate)` fallback and are removed entirely
+    /// when the feature is not enabled.
+    pub fn expand_item(&self, item: Item) -> TokenStream {
+        let features = self.feature_predicate();
+        let doc_cfg = self
+            .doc_cfg
+            .as_ref()
+            .unwrap_or(&CfgFlag::Enabled)
+            .doc_cfg_attr(&features);
+        let predicate = self
+            .docs
+            .clone()
+            .unwrap_or(CfgFlag::Disabled)
+            .gate_predicate(features);
+        expand_gated(item, &predicate, doc_cfg, vec![self.doc_attr()])
+    }
+
+    fn doc_attr(&self) -> syn::Attribute {
+        let feature = &self.feature;
+        let doc = formatdoc! {"
+            # Availability
+
+            This API is only available when the `{feature}` crate feature is enabled."};
+        parse_quote! { #[doc = #doc] }
+    }
+
+    fn feature_predicate(&self) -> TokenStream {
+        let feature = &self.feature;
+        quote! { feature = #feature }
+    }
+}
+
+/// Gate the item on the given cfg predicate, without a fallback, and add the documentation.
+pub fn expand_gated(
+    mut item: Item,
+    predicate: &TokenStream,
+    doc_cfg: TokenStream,
+    doc_attrs: Vec<syn::Attribute>,
+) -> TokenStream {
+    // Documentation on extern blocks and macro invocations is unused, and would cause warnings.
+    let documented = match &item {
+        Item::ForeignMod(_) | Item::Verbatim(_) => false,
+        Item::Macro(item_macro) => item_macro.ident.is_some(),
+        _ => true,
+    };
+    // Re-exports are documented by the items they re-export.
+    if documented && !matches!(item, Item::Use(_)) {
+        if let Some(attrs) = item_attrs_mut(&mut item) {
+            attrs.extend(doc_attrs);
+        }
+    }
+    let doc_cfg = if documented {
+        doc_cfg
+    } else {
+        TokenStream::new()
+    };
+    quote! {
+        #[cfg(#predicate)]
+        #doc_cfg
+        #item
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use quote::quote;
+    use syn::parse_quote;
+
+    use super::*;
+
+    const SERDE_DOC: &str =
+        "# Availability\n\nThis API is only available when the `serde` crate feature is enabled.";
+
+    #[test]
+    fn expand_struct() {
+        let item: Item = parse_quote! {
+            pub struct Foo;
+        };
+        let gated = GatedAttribute {
+            feature: "serde".to_string(),
+            ..Default::default()
+        };
+        let tokens = gated.expand_item(item);
+        let expected = quote! {
+            #[cfg(feature = "serde")]
+            #[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+            #[doc = #SERDE_DOC]
+            pub struct Foo;
+        };
+        assert_eq!(tokens.to_string(), expected.to_string());
+    }
+
+    #[test]
+    fn expand_trait_impl_with_docs() {
+        let item: Item = parse_quote! {
+            impl serde::Serialize for Foo {}
+        };
+        let gated = GatedAttribute {
+            feature: "serde".to_string(),
+            docs: Some(CfgFlag::Enabled),
+            doc_cfg: Some(CfgFlag::Disabled),
+        };
+        let tokens = gated.expand_item(item);
+        let expected = quote! {
+            #[cfg(any(doc, feature = "serde"))]
+            #[doc = #SERDE_DOC]
+            impl serde::Serialize for Foo {}
+        };
+        assert_eq!(tokens.to_string(), expected.to_string());
+    }
+
+    #[test]
+    fn expand_use() {
+        let item: Item = parse_quote! {
+            pub use serde::Serialize;
+        };
+        let gated = GatedAttribute {
+            feature: "serde".to_string(),
+            ..Default::default()
+        };
+        let tokens = gated.expand_item(item);
+        let expected = quote! {
+            #[cfg(feature = "serde")]
+            #[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+            pub use serde::Serialize;
+        };
+        assert_eq!(tokens.to_string(), expected.to_string());
+    }
+
+    #[test]
+    fn expand_macro_invocation() {
+        let item: Item = parse_quote! {
+            bitflags! {
+                pub struct Flags: u8 {}
+            }
+        };
+        let gated = GatedAttribute {
+            feature: "serde".to_string(),
+            ..Default::default()
+        };
+        let tokens = gated.expand_item(item);
+        let expected = quote! {
+            #[cfg(feature = "serde")]
+            bitflags! {
+                pub struct Flags: u8 {}
+            }
+        };
+        assert_eq!(tokens.to_string(), expected.to_string());
+    }
+}
@@ -1,9 +1,13 @@
+use proc_macro2::{Delimiter, TokenStream, TokenTree};
+use quote::ToTokens;
 use syn::Visibility;
 
 pub trait Stability {
     #[allow(unused)]
     fn attrs(&self) -> &[syn::Attribute];
 
+    fn attrs_mut(&mut self) -> &mut Vec<syn::Attribute>;
+
     fn push_attr(&mut self, attr: syn::Attribute);
 }
 
@@ -47,6 +51,10 @@ macro_rules! impl_item_like {
                 &self.attrs
             }
 
+            fn attrs_mut(&mut self) -> &mut Vec<syn::Attribute> {
+                &mut self.attrs
+            }
+
             fn push_attr(&mut self, attr: syn::Attribute) {
                 self.attrs.push(attr);
             }
@@ -81,13 +89,82 @@ impl_item_like!(
     syn::ItemStatic,
     #[allow(unused_imports)]
     syn::ItemUse,
+    syn::ForeignItemFn,
+    syn::ForeignItemStatic,
+    syn::ForeignItemType,
+    syn::ImplItemFn,
+    syn::ImplItemConst,
+    syn::ImplItemType,
 );
 
+/// An item with generic parameters that can be replaced by a type alias.
+pub trait Generic: ItemLike {
+    fn ident(&self) -> &syn::Ident;
+
+    fn generics(&self) -> &syn::Generics;
+
+    /// The fields of the item that carry their own visibility.
+    fn fields_mut(&mut self) -> Vec<&mut syn::Field> {
+        Vec::new()
+    }
+}
+
+/// Implement `Generic` for the given types, optionally with the name of their fields.
+macro_rules! impl_generic {
+    ($($ty:ty $(: $fields:ident)?),+ $(,)?) => {
+        $(
+            impl Generic for $ty {
+                fn ident(&self) -> &syn::Ident {
+                    &self.ident
+                }
+
+                fn generics(&self) -> &syn::Generics {
+                    &self.generics
+                }
+
+                $(
+                    fn fields_mut(&mut self) -> Vec<&mut syn::Field> {
+                        self.$fields.iter_mut().collect()
+                    }
+                )?
+            }
+        )+
+    };
+}
+
+impl_generic!(syn::ItemStruct: fields, syn::ItemEnum, syn::ItemType);
+
+/// The visibility in a child module that is equivalent to the given visibility in its parent.
+///
+/// E.g. a private item becomes `pub(super)`, and a `pub(super)` item becomes
+/// `pub(in super::super)`.
+pub fn child_module_visibility(visibility: &Visibility) -> Visibility {
+    match visibility {
+        Visibility::Public(_) => visibility.clone(),
+        Visibility::Inherited => syn::parse_quote! { pub(super) },
+        Visibility::Restricted(restricted) => {
+            let path = &restricted.path;
+            match path.segments.first() {
+                Some(first) if first.ident == "self" => {
+                    let rest = path.segments.iter().skip(1);
+                    syn::parse_quote! { pub(in super #(::#rest)*) }
+                }
+                Some(first) if first.ident == "super" => syn::parse_quote! { pub(in super::#path) },
+                _ => visibility.clone(),
+            }
+        }
+    }
+}
+
 impl Stability for syn::ItemStruct {
     fn attrs(&self) -> &[syn::Attribute] {
         &self.attrs
     }
 
+    fn attrs_mut(&mut self) -> &mut Vec<syn::Attribute> {
+        &mut self.attrs
+    }
+
     fn push_attr(&mut self, attr: syn::Attribute) {
         self.attrs.push(attr);
     }
@@ -114,12 +191,292 @@ impl ItemLike for syn::ItemStruct {
     }
 }
 
+impl Stability for syn::ItemUnion {
+    fn attrs(&self) -> &[syn::Attribute] {
+        &self.attrs
+    }
+
+    fn attrs_mut(&mut self) -> &mut Vec<syn::Attribute> {
+        &mut self.attrs
+    }
+
+    fn push_attr(&mut self, attr: syn::Attribute) {
+        self.attrs.push(attr);
+    }
+}
+
+impl ItemLike for syn::ItemUnion {
+    fn visibility(&self) -> &Visibility {
+        &self.vis
+    }
+
+    fn set_visibility(&mut self, visibility: Visibility) {
+        // Also constrain visibility of all fields to be at most the given
+        // item visibility.
+        self.fields
+            .named
+            .iter_mut()
+            .filter(|field| matches!(&field.vis, Visibility::Public(_)))
+            .for_each(|field| field.vis = visibility.clone());
+
+        self.vis = visibility;
+    }
+
+    fn allowed_lints(&self) -> Vec<syn::Ident> {
+        vec![syn::Ident::new("dead_code", proc_macro2::Span::call_site())]
+    }
+}
+
+impl Stability for syn::ItemMacro {
+    fn attrs(&self) -> &[syn::Attribute] {
+        &self.attrs
+    }
+
+    fn attrs_mut(&mut self) -> &mut Vec<syn::Attribute> {
+        &mut self.attrs
+    }
+
+    fn push_attr(&mut self, attr: syn::Attribute) {
+        self.attrs.push(attr);
+    }
+}
+
+/// The attributes of an item, or `None` for item types that carry no attributes.
+pub fn item_attrs_mut(item: &mut syn::Item) -> Option<&mut Vec<syn::Attribute>> {
+    use syn::Item;
+    match item {
+        Item::Const(item) => Some(&mut item.attrs),
+        Item::Enum(item) => Some(&mut item.attrs),
+        Item::ExternCrate(item) => Some(&mut item.attrs),
+        Item::Fn(item) => Some(&mut item.attrs),
+        Item::ForeignMod(item) => Some(&mut item.attrs),
+        Item::Impl(item) => Some(&mut item.attrs),
+        Item::Macro(item) => Some(&mut item.attrs),
+        Item::Mod(item) => Some(&mut item.attrs),
+        Item::Static(item) => Some(&mut item.attrs),
+        Item::Struct(item) => Some(&mut item.attrs),
+        Item::Trait(item) => Some(&mut item.attrs),
+        Item::TraitAlias(item) => Some(&mut item.attrs),
+        Item::Type(item) => Some(&mut item.attrs),
+        Item::Union(item) => Some(&mut item.attrs),
+        Item::Use(item) => Some(&mut item.attrs),
+        _ => None,
+    }
+}
+
+/// The visibility of an item that the stability attributes can be applied to, or `None` for other
+/// item types.
+pub fn item_visibility(item: &syn::Item) -> Option<&syn::Visibility> {
+    use syn::Item;
+    match item {
+        Item::Const(item) => Some(&item.vis),
+        Item::Enum(item) => Some(&item.vis),
+        Item::Fn(item) => Some(&item.vis),
+        Item::Mod(item) => Some(&item.vis),
+        Item::Static(item) => Some(&item.vis),
+        Item::Struct(item) => Some(&item.vis),
+        Item::Trait(item) => Some(&item.vis),
+        Item::Type(item) => Some(&item.vis),
+        Item::Union(item) => Some(&item.vis),
+        Item::Use(item) => Some(&item.vis),
+        _ => None,
+    }
+}
+
+/// The name of the attribute if it is one of this crate's attributes.
+///
+/// E.g. this returns `unstable` for `#[instability::unstable(...)]`. The `stable` and `unstable`
+/// attributes are also recognized when they are imported and used without the crate name.
+pub fn instability_attr_name(attr: &syn::Attribute) -> Option<String> {
+    let segments = &attr.path().segments;
+    match segments.len() {
+        1 => {
+            let name = segments[0].ident.to_string();
+            matches!(name.as_str(), "stable" | "unstable").then_some(name)
+        }
+        2 if segments[0].ident == "instability" => Some(segments[1].ident.to_string()),
+        _ => None,
+    }
+}
+
+/// The first of this crate's attributes with the given name, e.g. `stable`.
+pub fn find_instability_attr<'a>(
+    attrs: &'a [syn::Attribute],
+    name: &str,
+) -> Option<&'a syn::Attribute> {
+    attrs
+        .iter()
+        .find(|attr| instability_attr_name(attr).as_deref() == Some(name))
+}
+
+/// Whether the attributes include a `stable` or `unstable` attribute, which takes precedence over
+/// the stability of an enclosing module or `impl` block.
+pub fn has_stability_attr(attrs: &[syn::Attribute]) -> bool {
+    attrs.iter().any(|attr| {
+        matches!(
+            instability_attr_name(attr).as_deref(),
+            Some("stable" | "unstable")
+        )
+    })
+}
+
+/// Whether the given attribute is `#[macro_export]`.
+pub fn is_macro_export(attr: &syn::Attribute) -> bool {
+    attr.path().is_ident("macro_export")
+}
+
+/// Whether the given attribute marks a function as a procedural macro entry point.
+pub fn is_proc_macro_entry(attr: &syn::Attribute) -> bool {
+    let path = attr.path();
+    path.is_ident("proc_macro")
+        || path.is_ident("proc_macro_derive")
+        || path.is_ident("proc_macro_attribute")
+}
+
+/// Whether the given attribute exports a symbol regardless of the item's visibility.
+///
+/// This matches `#[no_mangle]` and `#[export_name = "..."]`, including their `#[unsafe(...)]` forms.
+pub fn is_symbol_export(attr: &syn::Attribute) -> bool {
+    fn is_symbol_export_path(path: &syn::Path) -> bool {
+        path.is_ident("no_mangle") || path.is_ident("export_name")
+    }
+    match &attr.meta {
+        syn::Meta::List(list) if list.path.is_ident("unsafe") => list
+            .parse_args::<syn::Meta>()
+            .is_ok_and(|meta| is_symbol_export_path(meta.path())),
+        meta => is_symbol_export_path(meta.path()),
+    }
+}
+
+/// Insert attributes in front of each public item in the input of an item-producing macro
+/// invocation.
+///
+/// Attributes on a macro invocation itself are not applied to the items it produces, so they are
+/// instead inserted into the macro's input, after the outer attributes (such as the documentation)
+/// of each item. This relies on the macro accepting outer attributes on the items it produces, as
+/// e.g. `bitflags!` does, so an error is returned if the input does not declare any public items.
+pub fn insert_macro_attrs(
+    tokens: TokenStream,
+    attrs: &[syn::Attribute],
+) -> syn::Result<TokenStream> {
+    let tokens = tokens.into_iter().collect::<Vec<_>>();
+    let item_starts = macro_item_starts(&tokens);
+    if item_starts.is_empty() {
+        return Err(syn::Error::new_spanned(
+            tokens.into_iter().collect::<TokenStream>(),
+            "expected the macro input to declare public items, e.g. `pub struct Foo;`, so that \
+             the stability attributes can be added to them",
+        ));
+    }
+    let mut output = TokenStream::new();
+    for (index, token) in tokens.into_iter().enumerate() {
+        if item_starts.contains(&index) {
+            attrs.iter().for_each(|attr| attr.to_tokens(&mut output));
+        }
+        output.extend([token]);
+    }
+    Ok(output)
+}
+
+/// Replace the unrestricted `pub` of each item in the input of a macro invocation with the given
+/// visibility.
+///
+/// Visibilities that are already restricted (e.g. `pub(crate)`) and those of nested items such as
+/// fields are left unchanged.
+pub fn restrict_pub_tokens(tokens: TokenStream, visibility: &Visibility) -> TokenStream {
+    let tokens = tokens.into_iter().collect::<Vec<_>>();
+    let item_starts = macro_item_starts(&tokens);
+    let mut output = TokenStream::new();
+    for (index, token) in tokens.iter().enumerate() {
+        if item_starts.contains(&index) && !is_restriction(tokens.get(index + 1)) {
+            visibility.to_tokens(&mut output);
+        } else {
+            output.extend([token.clone()]);
+        }
+    }
+    output
+}
+
+/// The positions of the `pub` tokens that start an item in the input of a macro invocation.
+///
+/// Only `pub` tokens at the top level of the input are considered, when they are the first token
+/// or follow an outer attribute or the end of the previous item (a `;` or a `{ ... }` body).
+fn macro_item_starts(tokens: &[TokenTree]) -> Vec<usize> {
+    let starts_item = |index: usize| match index.checked_sub(1).map(|index| &tokens[index]) {
+        None => true,
+        Some(TokenTree::Punct(punct)) => punct.as_char() == ';',
+        Some(TokenTree::Group(group)) => match group.delimiter() {
+            Delimiter::Brace => true,
+            Delimiter::Bracket => index.checked_sub(2).is_some_and(
+                |index| matches!(&tokens[index], TokenTree::Punct(punct) if punct.as_char() == '#'),
+            ),
+            _ => false,
+        },
+        _ => false,
+    };
+    tokens
+        .iter()
+        .enumerate()
+        .filter(|(index, token)| {
+            matches!(token, TokenTree::Ident(ident) if ident == "pub") && starts_item(*index)
+        })
+        .map(|(index, _)| index)
+        .collect()
+}
+
+/// Whether the token following a `pub` is a restriction such as `(crate)` or `(in path)`.
+fn is_restriction(token: Option<&TokenTree>) -> bool {
+    let Some(TokenTree::Group(group)) = token else {
+        return false;
+    };
+    group.delimiter() == Delimiter::Parenthesis
+        && matches!(
+            group.stream().into_iter().next(),
+            Some(TokenTree::Ident(ident))
+                if ident == "crate" || ident == "self" || ident == "super" || ident == "in"
+        )
+}
+
 impl Stability for syn::ItemImpl {
     fn attrs(&self) -> &[syn::Attribute] {
         &self.attrs
     }
 
+    fn attrs_mut(&mut self) -> &mut Vec<syn::Attribute> {
+        &mut self.attrs
+    }
+
     fn push_attr(&mut self, attr: syn::Attribute) {
         self.attrs.push(attr);
     }
 }
+
+/// A compile error for an item that the attribute can't be applied to, spanned on the item.
+pub fn unsupported_item(item: impl ToTokens) -> TokenStream {
+    syn::Error::new_spanned(item, "unsupported item type").to_compile_error()
+}
+
+/// Prepare a `pub use` item to carry its own stability documentation.
+///
+/// Rustdoc only renders the documentation of a re-export when the re-exported item is inlined, so
+/// this adds `#[doc(inline)]` to the item. Glob imports cannot be inlined as a single item, so an
+/// error is returned for those instead.
+pub fn inline_reexport(item: &mut syn::ItemUse) -> syn::Result<()> {
+    if let Some(glob) = find_glob(&item.tree) {
+        return Err(syn::Error::new_spanned(
+            glob,
+            "`inline` cannot be used with glob re-exports, re-export each item by name instead",
+        ));
+    }
+    item.attrs.push(syn::parse_quote! { #[doc(inline)] });
+    Ok(())
+}
+
+fn find_glob(tree: &syn::UseTree) -> Option<&syn::UseGlob> {
+    match tree {
+        syn::UseTree::Path(path) => find_glob(&path.tree),
+        syn::UseTree::Group(group) => group.items.iter().find_map(find_glob),
+        syn::UseTree::Glob(glob) => Some(glob),
+        syn::UseTree::Name(_) | syn::UseTree::Rename(_) => None,
+    }
+}
@@ -0,0 +1,200 @@
+use darling::{ast::NestedMeta, Error, FromMeta};
+use indoc::formatdoc;
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{parse_quote, Item};
+
+use crate::{
+    cfg::CfgFlag, gated::expand_gated, unstable::issue_and_note_doc_attrs, version::Version,
+};
+
+pub fn legacy_macro(args: TokenStream, input: TokenStream) -> TokenStream {
+    let attributes = match NestedMeta::parse_meta_list(args) {
+        Ok(attributes) => attributes,
+        Err(err) => return Error::from(err).write_errors(),
+    };
+    let legacy_attribute = match LegacyAttribute::from_list(&attributes) {
+        Ok(attributes) => attributes,
+        Err(err) => return err.write_errors(),
+    };
+    match syn::parse2::<Item>(input) {
+        Ok(item) => legacy_attribute.expand_item(item),
+        Err(err) => Error::from(err).write_errors(),
+    }
+}
+
+#[derive(Debug, Default, FromMeta)]
+pub struct LegacyAttribute {
+    /// The name of the feature that keeps the legacy API available.
+    ///
+    /// This will have the string `legacy-` prepended to it.
+    feature: String,
+
+    /// The version in which the item will be removed.
+    removed_in: Option<Version>,
+
+    /// A link or reference to a tracking issue for the removal.
+    issue: Option<String>,
+
+    /// An additional note that will be appended to the item's documentation, e.g. what to use
+    /// instead.
+    note: Option<String>,
+
+    /// Whether the item is included when generating documentation, even if the feature is not
+    /// enabled.
+    ///
+    /// This can be `true`, `false`, or the name of a cfg that must also be set when generating
+    /// documentation. Defaults to `true` unless the crate using the attribute is documented with
+    /// the `instability_disable_unstable_docs` cfg set.
+    docs: Option<CfgFlag>,
+
+    /// The cfg under which a `doc(cfg(...))` attribute is added to the item.
+    ///
+    /// This can be `true` (the default, using `docsrs`), `false` to not add the attribute, or the
+    /// name of a custom cfg.
+    doc_cfg: Option<CfgFlag>,
+}
+
+impl LegacyAttribute {
+    /// Gate the item on the legacy feature, and document it as deprecated.
+    ///
+    /// Unlike unstable items, legacy items have no `pub(crate)` fallback, as the crate itself
+    /// should no longer use them.
+    pub fn expand_item(&self, item: Item) -> TokenStream {
+        let feature_flag = self.feature_flag();
+        let features = quote! { feature = #feature_flag };
+        let doc_cfg = self
+            .doc_cfg
+            .as_ref()
+            .unwrap_or(&CfgFlag::Enabled)
+            .doc_cfg_attr(&features);
+        let predicate = CfgFlag::gate_predicate_or_default(self.docs.as_ref(), features);
+        let mut attrs = self.doc_attrs();
+        // `#[deprecated]` is rejected on trait impl blocks, and has no effect on extern blocks and
+        // macro invocations.
+        let deprecatable = match &item {
+            Item::Impl(item_impl) => item_impl.trait_.is_none(),
+            Item::ForeignMod(_) | Item::Verbatim(_) => false,
+            Item::Macro(item_macro) => item_macro.ident.is_some(),
+            _ => true,
+        };
+        if deprecatable {
+            attrs.push(self.deprecated_attr());
+        }
+        expand_gated(item, &predicate, doc_cfg, attrs)
+    }
+
+    fn doc_attrs(&self) -> Vec<syn::Attribute> {
+        let feature_flag = self.feature_flag();
+        let removal = match &self.removed_in {
+            Some(removed_in) => format!("version {removed_in}"),
+            None => String::from("a future version"),
+        };
+        let doc = formatdoc! {"
+            # Stability
+
+            **This API is deprecated** and is only available when the `{feature_flag}` crate
+            feature is enabled. It will be removed in {removal}."};
+        let mut attrs = vec![parse_quote! { #[doc = #doc] }];
+        attrs.extend(issue_and_note_doc_attrs(
+            self.issue.as_deref(),
+            self.note.as_deref(),
+        ));
+        attrs
+    }
+
+    /// The `#[deprecated]` attribute, so that users of the item get a warning.
+    fn deprecated_attr(&self) -> syn::Attribute {
+        let mut note = match &self.removed_in {
+            Some(removed_in) => format!("This API will be removed in version {removed_in}."),
+            None => String::from("This API will be removed in a future version."),
+        };
+        if let Some(extra) = &self.note {
+            note = format!("{note} {extra}");
+        }
+        parse_quote! { #[deprecated(note = #note)] }
+    }
+
+    fn feature_flag(&self) -> String {
+        format!("legacy-{}", self.feature)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use quote::quote;
+    use syn::parse_quote;
+
+    use super::*;
+
+    #[test]
+    fn expand_fn() {
+        let item: Item = parse_quote! {
+            pub fn foo() {}
+        };
+        let legacy = LegacyAttribute {
+            feature: "foo".to_string(),
+            removed_in: Some(Version::parse("0.30.0").unwrap()),
+            note: Some("Use `bar` instead.".to_string()),
+            ..Default::default()
+        };
+        let tokens = legacy.expand_item(item);
+        let doc = "# Stability\n\n**This API is deprecated** and is only available when the `legacy-foo` crate\nfeature is enabled. It will be removed in version 0.30.0.";
+        let expected = quote! {
+            #[cfg(any(all(doc, not(instability_disable_unstable_docs)), feature = "legacy-foo"))]
+            #[cfg_attr(docsrs, doc(cfg(feature = "legacy-foo")))]
+            #[doc = #doc]
+            #[doc = ""]
+            #[doc = "Use `bar` instead."]
+            #[deprecated(note = "This API will be removed in version 0.30.0. Use `bar` instead.")]
+            pub fn foo() {}
+        };
+        assert_eq!(tokens.to_string(), expected.to_string());
+    }
+
+    #[test]
+    fn expand_without_removed_in() {
+        let item: Item = parse_quote! {
+            pub struct Foo;
+        };
+        let legacy = LegacyAttribute {
+            feature: "foo".to_string(),
+            docs: Some(CfgFlag::Disabled),
+            doc_cfg: Some(CfgFlag::Disabled),
+            ..Default::default()
+        };
+        let tokens = legacy.expand_item(item);
+        let doc = "# Stability\n\n**This API is deprecated** and is only available when the `legacy-foo` crate\nfeature is enabled. It will be removed in a future version.";
+        let expected = quote! {
+            #[cfg(feature = "legacy-foo")]
+            #[doc = #doc]
+            #[deprecated(note = "This API will be removed in a future version.")]
+            pub struct Foo;
+        };
+        assert_eq!(tokens.to_string(), expected.to_string());
+    }
+
+    #[test]
+    fn expand_trait_impl_with_issue() {
+        let item: Item = parse_quote! {
+            impl Default for Foo {}
+        };
+        let legacy = LegacyAttribute {
+            feature: "foo".to_string(),
+            issue: Some("#123".to_string()),
+            docs: Some(CfgFlag::Disabled),
+            doc_cfg: Some(CfgFlag::Disabled),
+            ..Default::default()
+        };
+        let tokens = legacy.expand_item(item);
+        let doc = "# Stability\n\n**This API is deprecated** and is only available when the `legacy-foo` crate\nfeature is enabled. It will be removed in a future version.";
+        let expected = quote! {
+            #[cfg(feature = "legacy-foo")]
+            #[doc = #doc]
+            #[doc = "The tracking issue is: `#123`."]
+            impl Default for Foo {}
+        };
+        assert_eq!(tokens.to_string(), expected.to_string());
+    }
+}
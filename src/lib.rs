@@ -18,13 +18,28 @@
 //! [`instability-example`]: https://github.com/ratatui/instability/tree/main/example
 //! [`unstable`]: macro@unstable
 
+use api::api_macro;
+use build_cfgs::emit_build_cfgs_macro;
+use gated::gated_macro;
+use legacy::legacy_macro;
 use proc_macro::TokenStream;
+use requires_rust::requires_rust_macro;
 use stable::stable_macro;
-use unstable::unstable_macro;
+use unstable::{
+    if_unstable_macro, is_enabled_macro, unstable_const_macro, unstable_derive_macro,
+    unstable_items_macro, unstable_macro, unstable_param_macro,
+};
 
+mod api;
+mod build_cfgs;
+mod cfg;
+mod gated;
 mod item_like;
+mod legacy;
+mod requires_rust;
 mod stable;
 mod unstable;
+mod version;
 
 /// Mark an API as unstable.
 ///
@@ -38,24 +53,44 @@ mod unstable;
 /// - Changes the visibility of the item from `pub` to `pub(crate)` unless a certain crate feature
 ///   is enabled. This ensures that internal code within the crate can always use the item, but
 ///   downstream consumers cannot access it unless they opt-in to the unstable API.
-/// - Annotated `impl` blocks will instead be removed.
+/// - Removes `#[no_mangle]` and `#[export_name]` attributes from the `pub(crate)` version of the
+///   item, so that functions and statics only export their symbol when the feature is enabled.
+/// - Annotated trait `impl` blocks will instead be removed. Annotated inherent `impl` blocks are
+///   kept, and the attribute is instead applied to each public method, constant and type within
+///   the block that does not have its own `stable` or `unstable` attribute.
+/// - Annotated procedural macro functions (`#[proc_macro]`, `#[proc_macro_derive]` and
+///   `#[proc_macro_attribute]`) will also be removed, as they cannot be made `pub(crate)`.
+/// - Annotated `macro_rules!` macros that are exported with `#[macro_export]` will only be exported
+///   when the feature is enabled. Either way, they remain usable within the crate, including by
+///   path from the module they are defined in (e.g. `crate::module::my_macro!()`).
+/// - Annotated `extern` blocks will instead apply the attribute to each public item within the
+///   block. The attribute can also be applied to individual items within an `extern` block.
+/// - Annotated item-producing macro invocations (such as `bitflags! { ... }`) will instead be removed
+///   unless the feature is enabled, or re-invoked with a restricted visibility if `fallback_vis` is
+///   given. The documentation is inserted in front of each public item declared at the top level
+///   of the macro's input (after the item's own attributes), so the macro must accept outer
+///   attributes on the items it produces. Macro invocations whose input does not declare any
+///   public items produce a compile error.
 /// - Changes the Visibility of certain child items of the annotated item (such as struct fields) to
 ///   match the item's visibility. Children that are not public will not be affected.
 /// - Appends an "Stability" section to the item's documentation that notes that the item is
 ///   unstable and indicates the name of the crate feature to enable it.
 ///
-/// Child items of annotated modules are unchanged, as it might be desirable to be able to re-export
-/// them even if the module visibility is restricted. You should apply the attribute to each item
-/// within the module with the same feature name if you want to restrict the module's contents
-/// itself and not just the module namespace.
-///
-/// Note that unlike the [`unstable`][std-unstable] attribute used in the standard library, this
-/// attribute does not apply itself recursively to child items.
+/// By default, child items of annotated modules are unchanged, as it might be desirable to be able
+/// to re-export them even if the module visibility is restricted. To restrict the module's contents
+/// itself and not just the module namespace, pass `recursive` to apply the attribute to each
+/// public child item as well, similar to the [`unstable`][std-unstable] attribute used in the
+/// standard library.
 ///
 /// [std-unstable]: https://rustc-dev-guide.rust-lang.org/stability.html
 ///
 /// Applying this attribute to non-`pub` items is pointless and does nothing.
 ///
+/// Multiple `unstable` attributes on the same item are merged, so that the item is only available
+/// when all of their features are enabled, with a single "Stability" section listing each feature.
+/// Options other than `feature`, `issue`, `reason`, `note`, `since` and `max_age` are taken from the
+/// first attribute. Marking an item as both `stable` and `unstable` is a compile error.
+///
 /// # Arguments
 ///
 /// The `unstable` attribute supports optional arguments that can be passed to control its behavior.
@@ -65,16 +100,81 @@ mod unstable;
 ///   guarded by a catch-all `unstable` feature.
 /// - `issue`: a link or reference to a tracking issue for the unstable feature. This will be
 ///   included in the item's documentation.
+/// - `reason`: why the API is still unstable, e.g. `"naming not final"`. This will be included in
+///   the item's documentation so that users can judge the risk of depending on it.
+/// - `note`: an additional note that will be appended to the item's documentation.
+/// - `since`: the version in which the item was introduced as unstable. This must be a valid
+///   [Semantic Versioning](https://semver.org) version, and will be included in the item's
+///   documentation as "Unstable since 0.28.0".
+/// - `max_age`: the number of releases after `since` that the item may remain unstable. When the
+///   version of the crate being compiled is more than this many minor releases (or any major
+///   release) past `since`, a compile-time warning is emitted so that stale unstable APIs are
+///   noticed. Requires `since`.
+/// - `inline`: when applied to a `pub use` re-export, marks the re-export with `#[doc(inline)]` and
+///   appends the "Stability" section to it. Without this, re-exports are only gated and not
+///   documented. Glob re-exports cannot be inlined and produce a compile error.
+/// - `recursive`: when applied to an inline module, also applies the attribute to each public item
+///   in the module (including nested inline modules) that does not have its own `stable` or
+///   `unstable` attribute. Applying this to a `mod foo;` declaration produces a compile error.
+/// - `nightly`: marks the item as also requiring a nightly compiler, for items that use unstable
+///   language or library features. The item is then only available when the crate feature is
+///   enabled and the compiler is a nightly build, as detected by the `instability_nightly` cfg from
+///   [`emit_build_cfgs`] in the crate's build script. Enabling the feature on a stable compiler
+///   silently leaves the item out instead of failing to compile, and there is no `pub(crate)`
+///   version of the item on stable compilers either. Any `#![feature(...)]` attributes the item
+///   needs should be gated on `cfg_attr(instability_nightly, ...)` rather than on the crate
+///   feature.
+/// - `fallback_vis`: for item-producing macro invocations, the visibility (e.g. `"pub(crate)"`) that
+///   replaces the `pub` of each item declared at the top level of the macro's input when it is
+///   re-invoked without the feature enabled. Nested visibilities, such as those of fields, are
+///   left unchanged.
+/// - `docs`: whether the item is included when generating documentation. See below.
+/// - `doc_cfg`: the cfg under which a `doc(cfg(feature = "..."))` attribute is added to the item, so
+///   that rustdoc displays the required feature. Defaults to `docsrs`. Set this to the name of
+///   another cfg (e.g. `doc_cfg = "ratatui_docs"`) if your docs are built with a different one, or
+///   to `false` to not emit the attribute at all (e.g. on toolchains where `doc_cfg` is
+///   unavailable).
 ///
 /// # Disabling during documentation generation
 ///
 /// By default, this macro will include the unstable item when generating documentation by gating
 /// using a composite configuration flag that includes `docs`. In some cases, this may not be
-/// desirable, such as when checking docs with `cargo-semver-checks`. You can disable this by adding
-/// the `instability_disable_unstable_docs` RUSTFLAGS configuration flag to your build script. E.g.:
+/// desirable, such as when checking docs with `cargo-semver-checks`. The `docs` argument controls
+/// this for each item, and is evaluated in the crate that uses the attribute:
+///
+/// - `docs = true` (the default) includes the item whenever documentation is generated.
+/// - `docs = false` only includes the item when the unstable feature is enabled.
+/// - `docs = "unstable_docs"` (or any other cfg name) only includes the item when documentation is
+///   generated with the given cfg set, e.g. `RUSTDOCFLAGS="--cfg unstable_docs"`. Crates using this
+///   should declare the cfg in their `check-cfg` lints configuration.
+///
+/// ```
+/// #[instability::unstable(feature = "risky-function", docs = false)]
+/// pub fn risky_function() {
+///     unimplemented!()
+/// }
+/// ```
+///
+/// Items without a `docs` argument are also left out of the documentation when the
+/// `instability_disable_unstable_docs` cfg is set while documenting the crate that uses the
+/// attribute. As this is evaluated in that crate, it can be set for a single crate of a workspace:
 ///
 /// ```shell
-/// RUSTFLAGS="--cfg instability_disable_unstable_docs" cargo build
+/// cargo rustdoc -p my-crate -- --cfg instability_disable_unstable_docs
+/// ```
+///
+/// or for every crate that is documented:
+///
+/// ```shell
+/// RUSTDOCFLAGS="--cfg instability_disable_unstable_docs" cargo doc
+/// ```
+///
+/// Crates should declare this cfg to avoid `unexpected_cfgs` warnings, either by calling
+/// [`emit_build_cfgs`] from their build script, or in their `Cargo.toml`:
+///
+/// ```toml
+/// [lints.rust]
+/// unexpected_cfgs = { level = "warn", check-cfg = ["cfg(instability_disable_unstable_docs)"] }
 /// ```
 ///
 /// This will not prevent the item from being compiled when the unstable feature flag is enabled.
@@ -119,7 +219,7 @@ mod unstable;
 /// }
 /// ```
 ///
-/// We can also apply the attribute to an `impl` block like so:
+/// We can also apply the attribute to a trait `impl` block like so:
 ///
 /// ```
 /// /// This structure is responsible for bar.
@@ -132,11 +232,333 @@ mod unstable;
 ///     }
 /// }
 /// ```
+///
+/// [`emit_build_cfgs`]: macro@emit_build_cfgs
 #[proc_macro_attribute]
 pub fn unstable(args: TokenStream, input: TokenStream) -> TokenStream {
     unstable_macro(args.into(), input.into()).into()
 }
 
+/// Mark derived trait implementations as unstable.
+///
+/// Derived trait implementations are part of a type's public API just like its methods, so
+/// deriving e.g. `Hash` or `serde::Serialize` is a stability commitment too. This attribute derives
+/// the given traits only when an unstable crate feature is enabled, and appends a "Stability"
+/// section to the type's documentation that lists the unstable trait implementations.
+///
+/// This can be applied to structs, enums and unions.
+///
+/// # Arguments
+///
+/// Every bare path passed to the attribute is a derive macro to gate, except for the `nightly`,
+/// `inline` and `recursive` flags. All other arguments are the same as for the [`unstable`]
+/// attribute and must be given as `name = value` pairs (e.g. `docs = false`), so that they can be
+/// told apart from the derives.
+///
+/// # Examples
+///
+/// ```
+/// /// A point in space.
+/// #[instability::unstable_derive(feature = "point-hash", Hash, PartialEq, Eq)]
+/// pub struct Point {
+///     pub x: i32,
+///     pub y: i32,
+/// }
+/// ```
+///
+/// This will essentially be expanded to the following:
+///
+/// ```
+/// /// A point in space.
+/// ///
+/// /// # Stability
+/// ///
+/// /// **The `Hash`, `PartialEq` and `Eq` trait implementations are marked as unstable** and are
+/// /// only available when the `unstable-point-hash` crate feature is enabled. This comes with no
+/// /// stability guarantees, and could be changed or removed at any time.
+/// #[cfg_attr(any(doc, feature = "unstable-point-hash"), derive(Hash, PartialEq, Eq))]
+/// pub struct Point {
+///     pub x: i32,
+///     pub y: i32,
+/// }
+/// ```
+///
+/// [`unstable`]: macro@unstable
+#[proc_macro_attribute]
+pub fn unstable_derive(args: TokenStream, input: TokenStream) -> TokenStream {
+    unstable_derive_macro(args.into(), input.into()).into()
+}
+
+/// Mark the `const`-ness of a function as unstable.
+///
+/// This is similar to the `rustc_const_unstable` attribute used in the standard library. The
+/// function itself is always available with its declared visibility, but it is only a `const fn`
+/// when the `unstable-const-<feature>` crate feature is enabled (or `unstable-const` if no feature
+/// is given). This lets you ship a function as stable while reserving the right to change whether
+/// it can be evaluated at compile time.
+///
+/// This attribute appends a "Stability" section to the function's documentation that notes that
+/// using it in a `const` context is unstable.
+///
+/// # Arguments
+///
+/// The arguments are the same as for the [`unstable`] attribute. The `feature` name will have
+/// `unstable-const-` prepended to it.
+///
+/// # Examples
+///
+/// ```
+/// /// Returns the answer.
+/// #[instability::unstable_const(feature = "answer")]
+/// pub fn answer() -> u32 {
+///     42
+/// }
+/// ```
+///
+/// This will essentially be expanded to the following:
+///
+/// ```
+/// /// Returns the answer.
+/// ///
+/// /// # Stability
+/// ///
+/// /// **Using this function in a `const` context is marked as unstable** and is only possible
+/// /// when the `unstable-const-answer` crate feature is enabled. Without it, this is not a
+/// /// `const fn`. This comes with no stability guarantees, and could be changed or removed at any
+/// /// time.
+/// #[cfg(any(doc, feature = "unstable-const-answer"))]
+/// pub const fn answer() -> u32 {
+///     42
+/// }
+///
+/// /// Returns the answer.
+/// #[cfg(not(any(doc, feature = "unstable-const-answer")))]
+/// pub fn answer() -> u32 {
+///     42
+/// }
+/// ```
+///
+/// [`unstable`]: macro@unstable
+#[proc_macro_attribute]
+pub fn unstable_const(args: TokenStream, input: TokenStream) -> TokenStream {
+    unstable_const_macro(args.into(), input.into()).into()
+}
+
+/// Mark generic parameters of a type as unstable.
+///
+/// This lets you add a new generic parameter with a default to a stable type while the parameter
+/// itself is still experimental, the way the standard library added allocators to `Vec<T, A =
+/// Global>`. It can be applied to structs with named fields, enums and type aliases. Tuple and unit
+/// structs produce a compile error, as their constructors are not available through a type alias.
+///
+/// When the unstable feature is not enabled, the item is moved into a hidden module and replaced by
+/// a public type alias that fixes the unstable parameters to their defaults. The item keeps its
+/// name, so e.g. its `Debug` output is unchanged, and private fields remain accessible to the rest
+/// of the module. Code within the crate that uses the type with its default parameters works
+/// either way, but code that is generic over the unstable parameters should itself be gated on the
+/// feature. As the hidden module imports the items of its parent module, the attribute can only be
+/// applied to items at module level, and not to items within a function body. Place it above any
+/// `#[derive]` attributes so that the derived implementations are moved along with the item.
+///
+/// A "Stability" section is appended to the item's documentation that notes which parameters are
+/// unstable.
+///
+/// # Arguments
+///
+/// Every bare identifier passed to the attribute is the name of an unstable generic parameter,
+/// which must have a default, except for the `nightly`, `inline` and `recursive` flags. All other
+/// arguments are the same as for the [`unstable`] attribute and must be given as `name = value`
+/// pairs. With `nightly`, the unstable parameters are fixed to their defaults on stable compilers.
+///
+/// # Examples
+///
+/// ```
+/// # pub struct Global;
+/// /// A list of items.
+/// #[instability::unstable_param(A, feature = "allocator")]
+/// pub struct List<T, A = Global> {
+///     items: Vec<T>,
+///     allocator: A,
+/// }
+/// # fn main() {}
+/// ```
+///
+/// This will essentially be expanded to the following:
+///
+/// ```
+/// # pub struct Global;
+/// /// A list of items.
+/// ///
+/// /// # Stability
+/// ///
+/// /// **The `A` generic parameter is marked as unstable** and can only be specified when the
+/// /// `unstable-allocator` crate feature is enabled. Otherwise it is always `Global`. This comes
+/// /// with no stability guarantees, and could be changed or removed at any time.
+/// #[cfg(any(doc, feature = "unstable-allocator"))]
+/// pub struct List<T, A = Global> {
+///     items: Vec<T>,
+///     allocator: A,
+/// }
+///
+/// #[cfg(not(any(doc, feature = "unstable-allocator")))]
+/// #[doc(hidden)]
+/// mod __instability_params_List {
+///     use super::*;
+///
+///     pub struct List<T, A = Global> {
+///         pub(super) items: Vec<T>,
+///         pub(super) allocator: A,
+///     }
+/// }
+///
+/// /// A list of items.
+/// ///
+/// /// # Stability
+/// ///
+/// /// ...
+/// #[cfg(not(any(doc, feature = "unstable-allocator")))]
+/// pub type List<T> = __instability_params_List::List<T, Global>;
+/// # fn main() {}
+/// ```
+///
+/// [`unstable`]: macro@unstable
+#[proc_macro_attribute]
+pub fn unstable_param(args: TokenStream, input: TokenStream) -> TokenStream {
+    unstable_param_macro(args.into(), input.into()).into()
+}
+
+/// Mark several APIs as unstable at once.
+///
+/// The macro takes the same arguments as the [`unstable`] attribute, followed by a `;` and any
+/// number of items. Each item is expanded as if the attribute was applied to it directly, so this
+/// is equivalent to repeating the attribute on each item. Unlike applying the attribute to a
+/// module with `recursive`, the items are not moved into a module, so their paths are unchanged.
+///
+/// Items that have their own `unstable` attribute are merged with the macro's arguments, as when
+/// stacking attributes, and items marked as `stable` produce a compile error.
+///
+/// # Examples
+///
+/// ```
+/// instability::unstable_items! {
+///     feature = "canvas", issue = "#123";
+///
+///     /// A canvas to draw on.
+///     pub struct Canvas;
+///
+///     /// Draws on the canvas.
+///     pub fn draw(canvas: &mut Canvas) {
+///         unimplemented!()
+///     }
+/// }
+/// ```
+///
+/// This will essentially be expanded to the following:
+///
+/// ```
+/// /// A canvas to draw on.
+/// #[instability::unstable(feature = "canvas", issue = "#123")]
+/// pub struct Canvas;
+///
+/// /// Draws on the canvas.
+/// #[instability::unstable(feature = "canvas", issue = "#123")]
+/// pub fn draw(canvas: &mut Canvas) {
+///     unimplemented!()
+/// }
+/// ```
+///
+/// [`unstable`]: macro@unstable
+#[proc_macro]
+pub fn unstable_items(input: TokenStream) -> TokenStream {
+    unstable_items_macro(input.into()).into()
+}
+
+/// Check whether an unstable feature is enabled.
+///
+/// This takes the same arguments as the [`unstable`] attribute, and expands to a `bool` that is
+/// `true` when an item with that attribute would be available. The feature name is derived in the
+/// same way, so `feature = "foo"` checks the `unstable-foo` crate feature, and leaving out the
+/// feature checks the catch-all `unstable` feature. The `docs` and `nightly` arguments are also
+/// taken into account.
+///
+/// This is useful for code within the crate that behaves differently when an unstable feature is
+/// enabled. The result can be used in `const` contexts.
+///
+/// # Examples
+///
+/// ```
+/// pub fn describe() -> &'static str {
+///     if instability::is_enabled!(feature = "fast-path") {
+///         "fast"
+///     } else {
+///         "slow"
+///     }
+/// }
+/// ```
+///
+/// This will essentially be expanded to the following:
+///
+/// ```
+/// pub fn describe() -> &'static str {
+///     if cfg!(any(doc, feature = "unstable-fast-path")) {
+///         "fast"
+///     } else {
+///         "slow"
+///     }
+/// }
+/// ```
+///
+/// # See also
+///
+/// - The [`if_unstable`] macro for choosing between code that only compiles in one of the cases.
+///
+/// [`unstable`]: macro@unstable
+/// [`if_unstable`]: macro@if_unstable
+#[proc_macro]
+pub fn is_enabled(input: TokenStream) -> TokenStream {
+    is_enabled_macro(input.into()).into()
+}
+
+/// Choose between two blocks depending on whether an unstable feature is enabled.
+///
+/// The macro takes the same arguments as the [`unstable`] attribute, followed by a `;` and a block
+/// that is used when an item with that attribute would be available. This can be followed by
+/// `else` and a block that is used otherwise. Only the chosen block is compiled, so each block can
+/// use items that are only available in that case.
+///
+/// See [`is_enabled`] for how the feature name is derived.
+///
+/// # Examples
+///
+/// ```
+/// pub fn describe() -> String {
+///     instability::if_unstable!(feature = "fast-path"; {
+///         String::from("fast")
+///     } else {
+///         String::from("slow")
+///     })
+/// }
+/// ```
+///
+/// This will essentially be expanded to the following:
+///
+/// ```
+/// pub fn describe() -> String {
+///     #[cfg(any(doc, feature = "unstable-fast-path"))]
+///     let value = { String::from("fast") };
+///     #[cfg(not(any(doc, feature = "unstable-fast-path")))]
+///     let value = { String::from("slow") };
+///     value
+/// }
+/// ```
+///
+/// [`unstable`]: macro@unstable
+/// [`is_enabled`]: macro@is_enabled
+#[proc_macro]
+pub fn if_unstable(input: TokenStream) -> TokenStream {
+    if_unstable_macro(input.into()).into()
+}
+
 /// Mark an API as stable.
 ///
 /// You can apply this attribute to an item in your public API that you would like to expose to
@@ -147,6 +569,12 @@ pub fn unstable(args: TokenStream, input: TokenStream) -> TokenStream {
 ///
 /// - Appends a "Stability" section to the item's documentation that notes that the item is stable
 ///   and indicates the version at which it was stabilized.
+/// - For inherent `impl` blocks, also appends the "Stability" section to each public method,
+///   constant and type within the block that does not have its own `stable` or `unstable`
+///   attribute, so that it is shown next to each item.
+/// - For item-producing macro invocations (such as `bitflags! { ... }`), inserts the "Stability"
+///   section in front of each public item declared at the top level of the macro's input, as for
+///   the [`unstable`] attribute.
 ///
 /// # Arguments
 ///
@@ -157,6 +585,13 @@ pub fn unstable(args: TokenStream, input: TokenStream) -> TokenStream {
 ///   marked as stable with no version information.
 /// - `issue`: a link or reference to a tracking issue for the stabilized feature. This will be
 ///   included in the item's documentation.
+/// - `inline`: when applied to a `pub use` re-export, marks the re-export with `#[doc(inline)]` and
+///   appends the "Stability" section to it. Glob re-exports cannot be inlined and produce a compile
+///   error.
+/// - `recursive`: when applied to an inline module, also appends the "Stability" section to each
+///   public item in the module (including nested inline modules) that does not have its own
+///   `stable` or `unstable` attribute. Applying this to a `mod foo;` declaration produces a compile
+///   error.
 ///
 /// # Examples
 ///
@@ -198,8 +633,9 @@ pub fn unstable(args: TokenStream, input: TokenStream) -> TokenStream {
 /// This attribute does not change the visibility of the annotated item. You should ensure that the
 /// item's visibility is set to `pub` if you want it to be part of your crate's public API.
 ///
-/// Re-exports (`pub use`) do not modify the re-exported item's stability or documentation, and they have
-/// no way to display the `since` and `issue` information.
+/// Re-exports (`pub use`) do not modify the re-exported item's stability or documentation. They only
+/// display the `since` and `issue` information when the `inline` argument is given, in which case
+/// the re-exported item is documented inline at the re-export's location.
 ///
 /// # See also
 ///
@@ -210,3 +646,313 @@ pub fn unstable(args: TokenStream, input: TokenStream) -> TokenStream {
 pub fn stable(args: TokenStream, input: TokenStream) -> TokenStream {
     stable_macro(args.into(), input.into()).into()
 }
+
+/// Mark an API as only available with an optional crate feature.
+///
+/// You can apply this attribute to items that are part of your stable API, but that are only
+/// available when an optional crate feature is enabled, such as implementations of traits from an
+/// optional dependency. This replaces writing the `cfg`, `doc(cfg)` and documentation attributes by
+/// hand, so that every feature-gated item is documented consistently.
+///
+/// This attribute does the following things to annotated items:
+///
+/// - Removes the item unless the crate feature is enabled. Unlike the [`unstable`] attribute, the
+///   feature name is used as is, and there is no `pub(crate)` fallback for use within the crate.
+/// - Adds a `doc(cfg(feature = "..."))` attribute when documenting with the `docsrs` cfg.
+/// - Appends an "Availability" section to the item's documentation that notes which crate feature
+///   the item requires. Re-exports, `extern` blocks and macro invocations are gated but not
+///   documented.
+///
+/// # Arguments
+///
+/// - `feature`: the name of the crate feature that enables the item. This is required.
+/// - `docs`: whether the item is included when generating documentation, even if the feature is
+///   not enabled. This can be `true`, `false` or the name of a cfg, as for the [`unstable`]
+///   attribute. Defaults to `false`, as gated items often depend on optional dependencies that are
+///   only available with the feature.
+/// - `doc_cfg`: the cfg under which a `doc(cfg(feature = "..."))` attribute is added to the item.
+///   Defaults to `docsrs`, and can be set to `false` to not add the attribute.
+///
+/// # Examples
+///
+/// ```
+/// # pub struct Color;
+/// /// Converts the color to a `crossterm` color.
+/// #[instability::gated(feature = "crossterm")]
+/// impl From<Color> for crossterm::style::Color {
+///     fn from(color: Color) -> Self {
+///         unimplemented!()
+///     }
+/// }
+/// ```
+///
+/// This will essentially be expanded to the following:
+///
+/// ```
+/// # pub struct Color;
+/// /// Converts the color to a `crossterm` color.
+/// ///
+/// /// # Availability
+/// ///
+/// /// This API is only available when the `crossterm` crate feature is enabled.
+/// #[cfg(feature = "crossterm")]
+/// #[cfg_attr(docsrs, doc(cfg(feature = "crossterm")))]
+/// impl From<Color> for crossterm::style::Color {
+///     fn from(color: Color) -> Self {
+///         unimplemented!()
+///     }
+/// }
+/// ```
+///
+/// # See also
+///
+/// - The [`unstable`] attribute for marking an API as unstable.
+///
+/// [`unstable`]: macro@unstable
+#[proc_macro_attribute]
+pub fn gated(args: TokenStream, input: TokenStream) -> TokenStream {
+    gated_macro(args.into(), input.into()).into()
+}
+
+/// Mark an API as deprecated and only kept available behind an opt-in crate feature.
+///
+/// You can apply this attribute to an item that has been removed from your public API, but that
+/// you would like to keep available for a few releases so that users have time to migrate away from
+/// it. This is the reverse of the [`unstable`] attribute: the item is only available when a
+/// `legacy-` feature is enabled, rather than an `unstable-` feature.
+///
+/// This attribute does the following things to annotated items:
+///
+/// - Removes the item unless the crate feature is enabled. There is no `pub(crate)` fallback, as
+///   the crate itself should no longer use the item.
+/// - Adds a `doc(cfg(feature = "legacy-..."))` attribute when documenting with the `docsrs` cfg.
+/// - Appends a "Stability" section to the item's documentation that notes that the item is
+///   deprecated, the crate feature that enables it, and the version in which it will be removed.
+/// - Adds a `#[deprecated]` attribute with the version in which the item will be removed and the
+///   `note`, so that users of the item get a warning. This is left out for trait `impl` blocks,
+///   which can't be deprecated.
+///
+/// # Arguments
+///
+/// - `feature`: the name of the feature that keeps the item available. This will have the string
+///   `legacy-` prepended to it. This is required.
+/// - `removed_in`: the version in which the item will be removed. This must be a valid
+///   [Semantic Versioning](https://semver.org) version.
+/// - `issue`: a link or reference to a tracking issue for the removal. This will be included in the
+///   item's documentation.
+/// - `note`: an additional note that will be appended to the item's documentation in its own
+///   paragraph and to the deprecation warning, e.g. what to use instead.
+/// - `docs` and `doc_cfg`: as for the [`unstable`] attribute.
+///
+/// # Examples
+///
+/// ```
+/// /// Renders the widget.
+/// #[instability::legacy(feature = "render", removed_in = "0.30.0", note = "Use `draw` instead.")]
+/// pub fn render() {
+///     unimplemented!()
+/// }
+/// ```
+///
+/// This will essentially be expanded to the following:
+///
+/// ```
+/// /// Renders the widget.
+/// ///
+/// /// # Stability
+/// ///
+/// /// **This API is deprecated** and is only available when the `legacy-render` crate
+/// /// feature is enabled. It will be removed in version 0.30.0.
+/// ///
+/// /// Use `draw` instead.
+/// #[cfg(any(doc, feature = "legacy-render"))]
+/// #[cfg_attr(docsrs, doc(cfg(feature = "legacy-render")))]
+/// #[deprecated(note = "This API will be removed in version 0.30.0. Use `draw` instead.")]
+/// pub fn render() {
+///     unimplemented!()
+/// }
+/// ```
+///
+/// # See also
+///
+/// - The [`unstable`] attribute for marking an API as unstable.
+///
+/// [`unstable`]: macro@unstable
+#[proc_macro_attribute]
+pub fn legacy(args: TokenStream, input: TokenStream) -> TokenStream {
+    legacy_macro(args.into(), input.into()).into()
+}
+
+/// Mark an API as only available in certain API levels.
+///
+/// You can apply this attribute to items that belong to a particular version of an API, so that a
+/// new, incompatible version of the API can be introduced while the previous version is still
+/// available. Each API level is selected by an `api-v<level>` crate feature, such as `api-v1`, and
+/// the latest API level is used if none of these features is enabled.
+///
+/// This attribute does the following things to annotated items:
+///
+/// - Removes the item unless the selected API level is within the item's range of API levels.
+///   There is no `pub(crate)` fallback, as items from different API levels may share a name.
+/// - Appends an "API level" section to the item's documentation that notes which API levels the
+///   item is available in.
+///
+/// Your crate should declare an `api-v<level>` feature for each API level before the latest one,
+/// and may declare one for the latest level so that users can keep using it once a new level is
+/// added. As only one API level can be used at a time, the features are mutually exclusive. Call
+/// [`emit_build_cfgs`] from your build script to fail the build when more than one of them is
+/// enabled. This check is skipped on docs.rs, which enables all features, or when the `docsrs`
+/// cfg is set. In that case the earliest enabled API level is documented.
+///
+/// # Arguments
+///
+/// - `added`: the API level in which the item was added. The item is not available when an earlier
+///   API level is selected.
+/// - `removed`: the API level in which the item was removed. The item is only available when an
+///   earlier API level is selected.
+///
+/// At least one of the arguments is required.
+///
+/// # Examples
+///
+/// ```
+/// /// Renders the widget with the original API.
+/// #[instability::api(removed = 2)]
+/// pub fn render() {
+///     unimplemented!()
+/// }
+///
+/// /// Renders the widget with the new API.
+/// #[instability::api(added = 2)]
+/// pub fn render() {
+///     unimplemented!()
+/// }
+/// ```
+///
+/// This will essentially be expanded to the following:
+///
+/// ```
+/// /// Renders the widget with the original API.
+/// ///
+/// /// # API level
+/// ///
+/// /// This API is only available in API level 1.
+/// ///
+/// /// The API level is selected with the `api-v<level>` crate features, and is the latest
+/// /// level if none of them is enabled.
+/// #[cfg(any(feature = "api-v1"))]
+/// pub fn render() {
+///     unimplemented!()
+/// }
+///
+/// /// Renders the widget with the new API.
+/// ///
+/// /// # API level
+/// ///
+/// /// This API is only available in API level 2 and later.
+/// ///
+/// /// The API level is selected with the `api-v<level>` crate features, and is the latest
+/// /// level if none of them is enabled.
+/// #[cfg(not(any(feature = "api-v1")))]
+/// pub fn render() {
+///     unimplemented!()
+/// }
+/// ```
+///
+/// # See also
+///
+/// - The [`unstable`] attribute for marking an API as unstable.
+///
+/// [`emit_build_cfgs`]: macro@emit_build_cfgs
+/// [`unstable`]: macro@unstable
+#[proc_macro_attribute]
+pub fn api(args: TokenStream, input: TokenStream) -> TokenStream {
+    api_macro(args.into(), input.into()).into()
+}
+
+/// Mark an API as requiring a newer version of Rust than the rest of the crate.
+///
+/// You can apply this attribute to items that need a newer compiler than your crate's minimum
+/// supported Rust version, for example because they use a recently stabilized language feature.
+/// The item is only available when the crate is compiled with at least the given version of Rust.
+///
+/// This relies on the `instability_rust` cfg, which is emitted by the [`emit_build_cfgs`] macro in
+/// your crate's build script. Without it, items with this attribute are never available.
+///
+/// This attribute does the following things to annotated items:
+///
+/// - Removes the item unless the crate is compiled with the given version of Rust or newer. There
+///   is no `pub(crate)` fallback, as the item cannot be compiled with older versions.
+/// - Appends a "Stability" section to the item's documentation that notes the version of Rust the
+///   item requires.
+///
+/// # Arguments
+///
+/// The attribute takes the minimum Rust version as a string, such as `"1.85"`.
+///
+/// # Examples
+///
+/// ```
+/// /// Returns the widget's cells.
+/// #[instability::requires_rust("1.85")]
+/// pub fn cells() -> impl Iterator<Item = char> {
+///     "abc".chars()
+/// }
+/// ```
+///
+/// This will essentially be expanded to the following:
+///
+/// ```
+/// /// Returns the widget's cells.
+/// ///
+/// /// # Stability
+/// ///
+/// /// This API requires Rust 1.85 or newer, and is not available when compiling with an
+/// /// older version.
+/// #[cfg(instability_rust = "1.85")]
+/// pub fn cells() -> impl Iterator<Item = char> {
+///     "abc".chars()
+/// }
+/// ```
+///
+/// [`emit_build_cfgs`]: macro@emit_build_cfgs
+#[proc_macro_attribute]
+pub fn requires_rust(args: TokenStream, input: TokenStream) -> TokenStream {
+    requires_rust_macro(args.into(), input.into()).into()
+}
+
+/// Emit the cfgs used by this crate's attributes from a build script.
+///
+/// Some attributes depend on information about the compiler that is only available to build
+/// scripts. Call this macro from your crate's `build.rs`, with `instability` added to your
+/// `[build-dependencies]`, to make that information available:
+///
+/// ```ignore
+/// // build.rs
+/// fn main() {
+///     instability::emit_build_cfgs!();
+/// }
+/// ```
+///
+/// This emits the following cfgs, and declares them so that they don't cause `unexpected_cfgs`
+/// warnings:
+///
+/// - `instability_rust = "1.<minor>"` for each version of Rust up to and including the version of
+///   the compiler, which is used by the [`requires_rust`] attribute.
+/// - `instability_nightly` when the compiler is a nightly or development build, which is used by
+///   the [`unstable`] attribute's `nightly` argument.
+///
+/// It also declares the `instability_disable_unstable_docs` cfg, which is never set by the build
+/// script but which unstable items are gated on (see [`unstable`]).
+///
+/// Finally, it fails the build when more than one `api-v<level>` crate feature is enabled, as only
+/// one API level can be selected at a time (see [`api`]). This check is skipped when the `DOCS_RS`
+/// environment variable or the `docsrs` cfg is set, as docs.rs builds with all features enabled.
+///
+/// [`api`]: macro@api
+/// [`requires_rust`]: macro@requires_rust
+/// [`unstable`]: macro@unstable
+#[proc_macro]
+pub fn emit_build_cfgs(input: TokenStream) -> TokenStream {
+    emit_build_cfgs_macro(input.into()).into()
+}
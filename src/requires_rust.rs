@@ -0,0 +1,93 @@
+use indoc::formatdoc;
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{parse_quote, Item};
+
+use crate::gated::expand_gated;
+
+pub fn requires_rust_macro(args: TokenStream, input: TokenStream) -> TokenStream {
+    let version = match syn::parse2::<syn::LitStr>(args).and_then(RustVersion::parse) {
+        Ok(version) => version,
+        Err(err) => return err.to_compile_error(),
+    };
+    match syn::parse2::<Item>(input) {
+        Ok(item) => version.expand_item(item),
+        Err(err) => err.to_compile_error(),
+    }
+}
+
+/// The minimum Rust version required by an item, e.g. `1.85`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RustVersion {
+    minor: u64,
+}
+
+impl RustVersion {
+    fn parse(value: syn::LitStr) -> syn::Result<Self> {
+        value
+            .value()
+            .strip_prefix("1.")
+            .and_then(|minor| minor.parse().ok())
+            .map(|minor| Self { minor })
+            .ok_or_else(|| {
+                syn::Error::new_spanned(value, "expected a Rust version such as `\"1.85\"`")
+            })
+    }
+
+    /// Gate the item on the `instability_rust` cfg emitted by the build script helper.
+    pub fn expand_item(&self, item: Item) -> TokenStream {
+        let version = self.to_string();
+        let doc = formatdoc! {"
+            # Stability
+
+            This API requires Rust {version} or newer, and is not available when compiling with an
+            older version."};
+        let predicate = quote! { instability_rust = #version };
+        expand_gated(
+            item,
+            &predicate,
+            TokenStream::new(),
+            vec![parse_quote! { #[doc = #doc] }],
+        )
+    }
+}
+
+impl std::fmt::Display for RustVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "1.{}", self.minor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use quote::quote;
+    use syn::parse_quote;
+
+    use super::*;
+
+    #[test]
+    fn parse_version() {
+        let version = RustVersion::parse(parse_quote! { "1.85" }).unwrap();
+        assert_eq!(version, RustVersion { minor: 85 });
+        for value in ["1", "1.85.0", "2.0", "1.x"] {
+            let value = syn::LitStr::new(value, proc_macro2::Span::call_site());
+            assert!(RustVersion::parse(value).is_err());
+        }
+    }
+
+    #[test]
+    fn expand_fn() {
+        let item: Item = parse_quote! {
+            pub fn foo() {}
+        };
+        let tokens = RustVersion { minor: 85 }.expand_item(item);
+        let doc = "# Stability\n\nThis API requires Rust 1.85 or newer, and is not available when compiling with an\nolder version.";
+        let expected = quote! {
+            #[cfg(instability_rust = "1.85")]
+            #[doc = #doc]
+            pub fn foo() {}
+        };
+        assert_eq!(tokens.to_string(), expected.to_string());
+    }
+}
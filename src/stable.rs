@@ -2,9 +2,12 @@ use darling::{ast::NestedMeta, Error, FromMeta};
 use indoc::formatdoc;
 use proc_macro2::TokenStream;
 use quote::ToTokens;
-use syn::{parse_quote, Item};
+use syn::{parse_quote, ForeignItem, ImplItem, Item, Visibility};
 
-use crate::item_like::{ItemLike, Stability};
+use crate::item_like::{
+    find_instability_attr, has_stability_attr, inline_reexport, insert_macro_attrs,
+    is_macro_export, item_attrs_mut, item_visibility, unsupported_item, ItemLike, Stability,
+};
 
 pub fn stable_macro(args: TokenStream, input: TokenStream) -> TokenStream {
     let attributes = match NestedMeta::parse_meta_list(args) {
@@ -15,22 +18,21 @@ pub fn stable_macro(args: TokenStream, input: TokenStream) -> TokenStream {
         Ok(attributes) => attributes,
         Err(err) => return err.write_errors(),
     };
-    match syn::parse2::<Item>(input) {
-        Ok(item) => match item {
-            Item::Type(item_type) => unstable_attribute.expand(item_type),
-            Item::Enum(item_enum) => unstable_attribute.expand(item_enum),
-            Item::Struct(item_struct) => unstable_attribute.expand(item_struct),
-            Item::Fn(item_fn) => unstable_attribute.expand(item_fn),
-            Item::Mod(item_mod) => unstable_attribute.expand(item_mod),
-            Item::Trait(item_trait) => unstable_attribute.expand(item_trait),
-            Item::Const(item_const) => unstable_attribute.expand(item_const),
-            Item::Static(item_static) => unstable_attribute.expand(item_static),
-            Item::Use(item_use) => unstable_attribute.expand_use(item_use),
-            Item::Impl(item_impl) => unstable_attribute.expand_impl(item_impl),
-            _ => panic!("unsupported item type"),
-        },
-        Err(err) => Error::from(err).write_errors(),
+    let mut item = match syn::parse2::<Item>(input) {
+        Ok(item) => item,
+        Err(err) => return Error::from(err).write_errors(),
+    };
+    let unstable_attr = item_attrs_mut(&mut item)
+        .and_then(|attrs| find_instability_attr(attrs, "unstable"))
+        .cloned();
+    if let Some(attr) = unstable_attr {
+        return syn::Error::new_spanned(
+            attr,
+            "an item cannot be marked as both stable and unstable",
+        )
+        .to_compile_error();
     }
+    unstable_attribute.expand_item(item)
 }
 
 #[derive(Debug, Default, FromMeta)]
@@ -40,9 +42,72 @@ pub struct StableAttribute {
 
     /// A link or reference to a tracking issue for the feature.
     issue: Option<String>,
+
+    /// Whether a `pub use` re-export should be inlined and carry its own stability documentation.
+    ///
+    /// This has no effect on items other than `pub use`.
+    #[darling(default)]
+    inline: bool,
+
+    /// Whether the attribute is also applied to each public item in an inline module that does
+    /// not already carry a stability attribute.
+    ///
+    /// This has no effect on items other than `mod`.
+    #[darling(default)]
+    recursive: bool,
 }
 
 impl StableAttribute {
+    pub fn expand_item(&self, item: Item) -> TokenStream {
+        match item {
+            Item::Type(item_type) => self.expand(item_type),
+            Item::Enum(item_enum) => self.expand(item_enum),
+            Item::Struct(item_struct) => self.expand(item_struct),
+            Item::Union(item_union) => self.expand(item_union),
+            Item::Fn(item_fn) => self.expand(item_fn),
+            Item::Mod(item_mod) => self.expand_mod(item_mod),
+            Item::Trait(item_trait) => self.expand(item_trait),
+            Item::Const(item_const) => self.expand(item_const),
+            Item::Static(item_static) => self.expand(item_static),
+            Item::Use(item_use) => self.expand_use(item_use),
+            Item::Impl(item_impl) => self.expand_impl_block(item_impl),
+            Item::Macro(item_macro) if item_macro.ident.is_some() => {
+                self.expand_macro_rules(item_macro)
+            }
+            Item::Macro(item_macro) => self.expand_macro_invocation(item_macro),
+            Item::ForeignMod(item_foreign_mod) => self.expand_foreign_mod(item_foreign_mod),
+            // Foreign items (e.g. `pub fn foo();`) can't be parsed as regular items.
+            Item::Verbatim(tokens) => match syn::parse2::<ForeignItem>(tokens.clone()) {
+                Ok(foreign_item) => self.expand_foreign_item(foreign_item),
+                Err(_) => unsupported_item(tokens),
+            },
+            item => unsupported_item(item),
+        }
+    }
+
+    /// Expand a module, applying the attribute to its public child items if `recursive` is set.
+    pub fn expand_mod(&self, mut item: syn::ItemMod) -> TokenStream {
+        if self.recursive {
+            let Some((_, items)) = &mut item.content else {
+                return syn::Error::new_spanned(
+                    &item,
+                    "`recursive` can only be applied to inline modules",
+                )
+                .to_compile_error();
+            };
+            for child in items.iter_mut() {
+                let public = item_visibility(child)
+                    .is_some_and(|visibility| matches!(visibility, Visibility::Public(_)));
+                let annotated =
+                    item_attrs_mut(child).is_some_and(|attrs| has_stability_attr(attrs));
+                if public && !annotated {
+                    *child = Item::Verbatim(self.expand_item(child.clone()));
+                }
+            }
+        }
+        self.expand(item)
+    }
+
     pub fn expand(&self, item: impl ItemLike + ToTokens + Clone) -> TokenStream {
         if !item.is_public() {
             // We only care about public items.
@@ -51,14 +116,98 @@ impl StableAttribute {
         self.expand_impl(item)
     }
 
-    pub fn expand_use(&self, item: impl ItemLike + ToTokens + Clone) -> TokenStream {
-        // We don't want to transform `pub use` items. Adding documentation has adverse effects.
+    pub fn expand_use(&self, mut item: syn::ItemUse) -> TokenStream {
+        // By default we don't transform `pub use` items. Adding documentation has adverse effects.
         // The reexported type can signal its own stability, the reexport itself can really only
-        // use the label that rustdoc renders.
+        // use the label that rustdoc renders. When inlined, the reexport is documented as its own
+        // item and can carry the stability information.
+        if !self.inline || !item.is_public() {
+            return item.into_token_stream();
+        }
+        if let Err(err) = inline_reexport(&mut item) {
+            return err.to_compile_error();
+        }
+        self.expand_impl(item)
+    }
+
+    pub fn expand_macro_rules(&self, item: syn::ItemMacro) -> TokenStream {
+        if !item.attrs.iter().any(is_macro_export) {
+            // We only care about exported macros.
+            return item.into_token_stream();
+        }
+        self.expand_impl(item)
+    }
+
+    /// Expand an item-producing macro invocation, such as `bitflags! { ... }`.
+    ///
+    /// The documentation is inserted in front of each public item in the macro's input, as
+    /// attributes on the invocation itself are not applied to the items it produces.
+    pub fn expand_macro_invocation(&self, mut item: syn::ItemMacro) -> TokenStream {
+        match insert_macro_attrs(item.mac.tokens, &self.doc_attrs()) {
+            Ok(tokens) => item.mac.tokens = tokens,
+            Err(err) => return err.to_compile_error(),
+        }
         item.into_token_stream()
     }
 
+    /// Expand an `extern` block by applying the attribute to each of its public items.
+    pub fn expand_foreign_mod(&self, mut item: syn::ItemForeignMod) -> TokenStream {
+        item.items = item
+            .items
+            .into_iter()
+            .map(|foreign_item| ForeignItem::Verbatim(self.expand_foreign_item(foreign_item)))
+            .collect();
+        item.into_token_stream()
+    }
+
+    /// Expand a single item of an `extern` block.
+    pub fn expand_foreign_item(&self, item: ForeignItem) -> TokenStream {
+        match item {
+            ForeignItem::Fn(item_fn) => self.expand(item_fn),
+            ForeignItem::Static(item_static) => self.expand(item_static),
+            ForeignItem::Type(item_type) => self.expand(item_type),
+            item => item.into_token_stream(),
+        }
+    }
+
+    /// Expand an `impl` block.
+    ///
+    /// For inherent `impl` blocks, each public item that isn't annotated itself is also documented,
+    /// as rustdoc shows the documentation of the block far away from the individual items. Items of
+    /// trait `impl` blocks are left alone, as documenting them would hide the trait's documentation
+    /// for those items.
+    pub fn expand_impl_block(&self, mut item: syn::ItemImpl) -> TokenStream {
+        if item.trait_.is_none() {
+            for impl_item in &mut item.items {
+                match impl_item {
+                    ImplItem::Fn(item_fn) => self.add_doc_unless_annotated(item_fn),
+                    ImplItem::Const(item_const) => self.add_doc_unless_annotated(item_const),
+                    ImplItem::Type(item_type) => self.add_doc_unless_annotated(item_type),
+                    _ => {}
+                }
+            }
+        }
+        self.expand_impl(item)
+    }
+
+    fn add_doc_unless_annotated(&self, item: &mut impl ItemLike) {
+        if item.is_public() && !has_stability_attr(item.attrs()) {
+            self.add_doc(item);
+        }
+    }
+
     pub fn expand_impl(&self, mut item: impl Stability + ToTokens) -> TokenStream {
+        self.add_doc(&mut item);
+        item.into_token_stream()
+    }
+
+    fn add_doc(&self, item: &mut impl Stability) {
+        self.doc_attrs()
+            .into_iter()
+            .for_each(|attr| item.push_attr(attr));
+    }
+
+    fn doc_attrs(&self) -> Vec<syn::Attribute> {
         let doc = if let Some(ref version) = self.since {
             formatdoc! {"
                 # Stability
@@ -72,13 +221,13 @@ impl StableAttribute {
 
                 This API is stable."}
         };
-        item.push_attr(parse_quote! { #[doc = #doc] });
+        let mut attrs = vec![parse_quote! { #[doc = #doc] }];
 
         if let Some(issue) = &self.issue {
             let doc = format!("The tracking issue is: `{}`.", issue);
-            item.push_attr(parse_quote! { #[doc = #doc] });
+            attrs.push(parse_quote! { #[doc = #doc] });
         }
-        item.into_token_stream()
+        attrs
     }
 }
 
@@ -109,7 +258,7 @@ mod tests {
         let item: syn::ItemType = parse_quote! { pub type Foo = Bar; };
         let stable = StableAttribute {
             since: Some("v1.0.0".to_string()),
-            issue: None,
+            ..Default::default()
         };
         let tokens = stable.expand(item);
         let expected = quote! {
@@ -123,8 +272,8 @@ mod tests {
     fn expand_with_issue() {
         let item: syn::ItemType = parse_quote! { pub type Foo = Bar; };
         let stable = StableAttribute {
-            since: None,
             issue: Some("#123".to_string()),
+            ..Default::default()
         };
         let tokens = stable.expand(item);
         let expected = quote! {
@@ -141,6 +290,7 @@ mod tests {
         let stable = StableAttribute {
             since: Some("v1.0.0".to_string()),
             issue: Some("#123".to_string()),
+            ..Default::default()
         };
         let tokens = stable.expand(item);
         let expected = quote! {
@@ -181,6 +331,24 @@ mod tests {
         assert_eq!(tokens.to_string(), expected.to_string());
     }
 
+    #[test]
+    fn expand_public_union() {
+        let item: syn::ItemUnion = parse_quote! {
+            pub union Foo {
+                pub a: u32,
+            }
+        };
+        let stable = StableAttribute::default();
+        let tokens = stable.expand(item);
+        let expected = quote! {
+            #[doc = #STABLE_DOC]
+            pub union Foo {
+                pub a: u32,
+            }
+        };
+        assert_eq!(tokens.to_string(), expected.to_string());
+    }
+
     #[test]
     fn expand_public_enum() {
         let item: syn::ItemEnum = parse_quote! {
@@ -279,6 +447,70 @@ mod tests {
         assert_eq!(tokens.to_string(), expected.to_string());
     }
 
+    #[test]
+    fn expand_recursive_mod() {
+        let item: syn::ItemMod = parse_quote! {
+            pub mod foo {
+                pub fn bar() {}
+                fn baz() {}
+                #[instability::unstable(feature = "qux")]
+                pub fn qux() {}
+            }
+        };
+        let stable = StableAttribute {
+            recursive: true,
+            ..Default::default()
+        };
+        let tokens = stable.expand_mod(item);
+        let expected = quote! {
+            #[doc = #STABLE_DOC]
+            pub mod foo {
+                #[doc = #STABLE_DOC]
+                pub fn bar() {}
+                fn baz() {}
+                #[instability::unstable(feature = "qux")]
+                pub fn qux() {}
+            }
+        };
+        assert_eq!(tokens.to_string(), expected.to_string());
+    }
+
+    #[test]
+    fn expand_recursive_mod_with_unstable_derive() {
+        let item: syn::ItemMod = parse_quote! {
+            pub mod foo {
+                #[instability::unstable_derive(feature = "qux", Hash)]
+                pub struct Bar;
+            }
+        };
+        let stable = StableAttribute {
+            recursive: true,
+            ..Default::default()
+        };
+        let tokens = stable.expand_mod(item);
+        let expected = quote! {
+            #[doc = #STABLE_DOC]
+            pub mod foo {
+                #[instability::unstable_derive(feature = "qux", Hash)]
+                #[doc = #STABLE_DOC]
+                pub struct Bar;
+            }
+        };
+        assert_eq!(tokens.to_string(), expected.to_string());
+    }
+
+    #[test]
+    fn stable_and_unstable_conflict() {
+        let tokens = stable_macro(
+            quote! { since = "v1.0.0" },
+            quote! {
+                #[instability::unstable(feature = "foo")]
+                pub type Foo = Bar;
+            },
+        );
+        assert!(tokens.to_string().contains("compile_error"));
+    }
+
     #[test]
     fn public_use_is_noop() {
         let item: syn::ItemUse = parse_quote! {
@@ -292,6 +524,108 @@ mod tests {
         assert_eq!(tokens.to_string(), expected.to_string());
     }
 
+    #[test]
+    fn expand_public_use_inline() {
+        let item: syn::ItemUse = parse_quote! {
+            pub use crate::foo::bar;
+        };
+        let stable = StableAttribute {
+            since: Some("v1.0.0".to_string()),
+            inline: true,
+            ..Default::default()
+        };
+        let tokens = stable.expand_use(item);
+        let expected = quote! {
+            #[doc(inline)]
+            #[doc = #SINCE_DOC]
+            pub use crate::foo::bar;
+        };
+        assert_eq!(tokens.to_string(), expected.to_string());
+    }
+
+    #[test]
+    fn expand_public_use_inline_glob() {
+        let item: syn::ItemUse = parse_quote! {
+            pub use crate::foo::*;
+        };
+        let stable = StableAttribute {
+            inline: true,
+            ..Default::default()
+        };
+        let tokens = stable.expand_use(item);
+        assert!(tokens.to_string().contains("compile_error"));
+    }
+
+    #[test]
+    fn expand_exported_macro_rules() {
+        let item: syn::ItemMacro = parse_quote! {
+            #[macro_export]
+            macro_rules! foo {
+                () => {};
+            }
+        };
+        let tokens = StableAttribute::default().expand_macro_rules(item);
+        let expected = quote! {
+            #[macro_export]
+            #[doc = #STABLE_DOC]
+            macro_rules! foo {
+                () => {};
+            }
+        };
+        assert_eq!(tokens.to_string(), expected.to_string());
+    }
+
+    #[test]
+    fn expand_macro_invocation() {
+        let item: syn::ItemMacro = parse_quote! {
+            bitflags! {
+                /// Flags.
+                pub struct Flags: u32 {
+                    const A = 1;
+                }
+            }
+        };
+        let tokens = StableAttribute::default().expand_macro_invocation(item);
+        let expected = quote! {
+            bitflags! {
+                /// Flags.
+                #[doc = #STABLE_DOC]
+                pub struct Flags: u32 {
+                    const A = 1;
+                }
+            }
+        };
+        assert_eq!(tokens.to_string(), expected.to_string());
+    }
+
+    #[test]
+    fn expand_macro_invocation_without_items() {
+        let item: syn::ItemMacro = parse_quote! {
+            newtype!(Meters, pub f64);
+        };
+        let tokens = StableAttribute::default().expand_macro_invocation(item);
+        assert!(tokens.to_string().contains("compile_error"));
+    }
+
+    #[test]
+    fn expand_foreign_mod() {
+        let item: syn::ItemForeignMod = parse_quote! {
+            extern "C" {
+                pub fn foo();
+                fn bar();
+            }
+        };
+        let tokens = StableAttribute::default().expand_foreign_mod(item);
+        let expected = quote! {
+            extern "C" {
+                #[doc = #STABLE_DOC]
+                pub fn foo();
+                fn bar();
+            }
+        };
+        assert_eq!(tokens.to_string(), expected.to_string());
+    }
+
     #[test]
     fn expand_impl_block() {
         let item: syn::ItemImpl = parse_quote! {
@@ -304,4 +638,77 @@ mod tests {
         };
         assert_eq!(tokens.to_string(), expected.to_string());
     }
+
+    #[test]
+    fn expand_inherent_impl_block() {
+        let item: syn::ItemImpl = parse_quote! {
+            impl Foo {
+                pub fn foo(&self) {}
+
+                #[instability::unstable(feature = "bar")]
+                pub fn bar(&self) {}
+
+                fn baz(&self) {}
+            }
+        };
+        let stable = StableAttribute {
+            since: Some("v1.0.0".to_string()),
+            ..Default::default()
+        };
+        let tokens = stable.expand_impl_block(item);
+        let expected = quote! {
+            #[doc = #SINCE_DOC]
+            impl Foo {
+                #[doc = #SINCE_DOC]
+                pub fn foo(&self) {}
+
+                #[instability::unstable(feature = "bar")]
+                pub fn bar(&self) {}
+
+                fn baz(&self) {}
+            }
+        };
+        assert_eq!(tokens.to_string(), expected.to_string());
+    }
+
+    #[test]
+    fn expand_inherent_impl_block_documents_unstable_const_items() {
+        let item: syn::ItemImpl = parse_quote! {
+            impl Foo {
+                #[instability::unstable_const(feature = "const-foo")]
+                pub fn foo(&self) {}
+            }
+        };
+        let tokens = StableAttribute::default().expand_impl_block(item);
+        let expected = quote! {
+            #[doc = #STABLE_DOC]
+            impl Foo {
+                #[instability::unstable_const(feature = "const-foo")]
+                #[doc = #STABLE_DOC]
+                pub fn foo(&self) {}
+            }
+        };
+        assert_eq!(tokens.to_string(), expected.to_string());
+    }
+
+    #[test]
+    fn expand_trait_impl_block_items_unchanged() {
+        let item: syn::ItemImpl = parse_quote! {
+            impl Default for Foo {
+                fn default() -> Self {
+                    Foo
+                }
+            }
+        };
+        let tokens = StableAttribute::default().expand_impl_block(item);
+        let expected = quote! {
+            #[doc = #STABLE_DOC]
+            impl Default for Foo {
+                fn default() -> Self {
+                    Foo
+                }
+            }
+        };
+        assert_eq!(tokens.to_string(), expected.to_string());
+    }
 }
@@ -1,39 +1,245 @@
 use darling::{ast::NestedMeta, Error, FromMeta};
 use indoc::formatdoc;
-use proc_macro2::TokenStream;
-use quote::{quote, ToTokens};
-use syn::{parse_quote, Item};
+use proc_macro2::{Span, TokenStream, TokenTree};
+use quote::{format_ident, quote, ToTokens};
+use syn::{
+    parse::{Parse, ParseStream, Parser},
+    parse_quote, ForeignItem, ImplItem, Item, Token, Visibility,
+};
 
-use crate::item_like::{ItemLike, Stability};
+use crate::{
+    cfg::CfgFlag,
+    item_like::{
+        child_module_visibility, find_instability_attr, has_stability_attr, inline_reexport,
+        insert_macro_attrs, instability_attr_name, is_macro_export, is_proc_macro_entry,
+        is_symbol_export, item_attrs_mut, item_visibility, restrict_pub_tokens, unsupported_item,
+        Generic, ItemLike, Stability,
+    },
+    version::Version,
+};
 
 pub fn unstable_macro(args: TokenStream, input: TokenStream) -> TokenStream {
     let attributes = match NestedMeta::parse_meta_list(args) {
         Ok(attributes) => attributes,
         Err(err) => return Error::from(err).write_errors(),
     };
-    let unstable_attribute = match UnstableAttribute::from_list(&attributes) {
+    let mut unstable_attribute = match UnstableAttribute::from_list(&attributes) {
+        Ok(attributes) => attributes,
+        Err(err) => return err.write_errors(),
+    };
+    let mut item = match syn::parse2::<Item>(input) {
+        Ok(item) => item,
+        Err(err) => return Error::from(err).write_errors(),
+    };
+    if let Err(err) = unstable_attribute.merge_stacked(&mut item) {
+        return err.write_errors();
+    }
+    unstable_attribute.expand_item(item)
+}
+
+pub fn unstable_derive_macro(args: TokenStream, input: TokenStream) -> TokenStream {
+    let attributes = match NestedMeta::parse_meta_list(args) {
+        Ok(attributes) => attributes,
+        Err(err) => return Error::from(err).write_errors(),
+    };
+    let (derives, options) = split_paths(attributes);
+    if derives.is_empty() {
+        return Error::custom("expected at least one derive, e.g. `Hash`").write_errors();
+    }
+    let unstable_attribute = match UnstableAttribute::from_list(&options) {
+        Ok(attributes) => attributes,
+        Err(err) => return err.write_errors(),
+    };
+    match syn::parse2::<Item>(input) {
+        Ok(item) => match item {
+            Item::Struct(item_struct) => unstable_attribute.expand_derive(item_struct, &derives),
+            Item::Enum(item_enum) => unstable_attribute.expand_derive(item_enum, &derives),
+            Item::Union(item_union) => unstable_attribute.expand_derive(item_union, &derives),
+            item => unsupported_item(item),
+        },
+        Err(err) => Error::from(err).write_errors(),
+    }
+}
+
+pub fn unstable_param_macro(args: TokenStream, input: TokenStream) -> TokenStream {
+    let attributes = match NestedMeta::parse_meta_list(args) {
+        Ok(attributes) => attributes,
+        Err(err) => return Error::from(err).write_errors(),
+    };
+    let (params, options) = split_paths(attributes);
+    let params = match params
+        .iter()
+        .map(|path| path.require_ident().cloned())
+        .collect::<syn::Result<Vec<_>>>()
+    {
+        Ok(params) if !params.is_empty() => params,
+        Ok(_) => return Error::custom("expected a generic parameter, e.g. `A`").write_errors(),
+        Err(err) => return Error::from(err).write_errors(),
+    };
+    let unstable_attribute = match UnstableAttribute::from_list(&options) {
+        Ok(attributes) => attributes,
+        Err(err) => return err.write_errors(),
+    };
+    match syn::parse2::<Item>(input) {
+        Ok(item) => match item {
+            Item::Struct(item_struct) if !matches!(item_struct.fields, syn::Fields::Named(_)) => {
+                syn::Error::new_spanned(
+                    &item_struct.ident,
+                    "`unstable_param` cannot be applied to tuple or unit structs, as their \
+                     constructors are not available through a type alias",
+                )
+                .to_compile_error()
+            }
+            Item::Struct(item_struct) => unstable_attribute.expand_params(item_struct, &params),
+            Item::Enum(item_enum) => unstable_attribute.expand_params(item_enum, &params),
+            Item::Type(item_type) => unstable_attribute.expand_params(item_type, &params),
+            item => unsupported_item(item),
+        },
+        Err(err) => Error::from(err).write_errors(),
+    }
+}
+
+/// The arguments of the `unstable` attribute that are given as bare words, e.g. `nightly`.
+const FLAGS: &[&str] = &["inline", "recursive", "nightly"];
+
+/// Split attribute arguments into bare paths and all other arguments, including flags.
+fn split_paths(attributes: Vec<NestedMeta>) -> (Vec<syn::Path>, Vec<NestedMeta>) {
+    let mut paths = Vec::new();
+    let mut options = Vec::new();
+    for attribute in attributes {
+        match attribute {
+            NestedMeta::Meta(syn::Meta::Path(path))
+                if !FLAGS.iter().any(|flag| path.is_ident(flag)) =>
+            {
+                paths.push(path)
+            }
+            attribute => options.push(attribute),
+        }
+    }
+    (paths, options)
+}
+
+pub fn unstable_const_macro(args: TokenStream, input: TokenStream) -> TokenStream {
+    let attributes = match NestedMeta::parse_meta_list(args) {
+        Ok(attributes) => attributes,
+        Err(err) => return Error::from(err).write_errors(),
+    };
+    let mut unstable_attribute = match UnstableAttribute::from_list(&attributes) {
         Ok(attributes) => attributes,
         Err(err) => return err.write_errors(),
     };
+    // The const-ness is gated by a separate `unstable-const-<feature>` feature.
+    unstable_attribute.feature = Some(match unstable_attribute.feature {
+        Some(feature) => format!("const-{feature}"),
+        None => String::from("const"),
+    });
     match syn::parse2::<Item>(input) {
         Ok(item) => match item {
-            Item::Type(item_type) => unstable_attribute.expand(item_type),
-            Item::Enum(item_enum) => unstable_attribute.expand(item_enum),
-            Item::Struct(item_struct) => unstable_attribute.expand(item_struct),
-            Item::Fn(item_fn) => unstable_attribute.expand(item_fn),
-            Item::Mod(item_mod) => unstable_attribute.expand(item_mod),
-            Item::Trait(item_trait) => unstable_attribute.expand(item_trait),
-            Item::Const(item_const) => unstable_attribute.expand(item_const),
-            Item::Static(item_static) => unstable_attribute.expand(item_static),
-            Item::Use(item_use) => unstable_attribute.expand_use(item_use),
-            Item::Impl(item_impl) => unstable_attribute.expand_impl(item_impl),
-            _ => panic!("unsupported item type"),
+            Item::Fn(item_fn) => unstable_attribute.expand_const(item_fn),
+            item => unsupported_item(item),
         },
         Err(err) => Error::from(err).write_errors(),
     }
 }
 
-#[derive(Debug, Default, FromMeta)]
+pub fn is_enabled_macro(input: TokenStream) -> TokenStream {
+    let attributes = match NestedMeta::parse_meta_list(input) {
+        Ok(attributes) => attributes,
+        Err(err) => return Error::from(err).write_errors(),
+    };
+    match UnstableAttribute::from_list(&attributes) {
+        Ok(unstable_attribute) => unstable_attribute.expand_is_enabled(),
+        Err(err) => err.write_errors(),
+    }
+}
+
+pub fn if_unstable_macro(input: TokenStream) -> TokenStream {
+    let Some((args, branches)) = split_at_semicolon(input.clone()) else {
+        return syn::Error::new_spanned(
+            input,
+            "expected `feature = \"...\"; { ... } else { ... }`",
+        )
+        .to_compile_error();
+    };
+    let attributes = match NestedMeta::parse_meta_list(args) {
+        Ok(attributes) => attributes,
+        Err(err) => return Error::from(err).write_errors(),
+    };
+    let unstable_attribute = match UnstableAttribute::from_list(&attributes) {
+        Ok(attributes) => attributes,
+        Err(err) => return err.write_errors(),
+    };
+    match syn::parse2::<Branches>(branches) {
+        Ok(branches) => unstable_attribute.expand_if_unstable(branches),
+        Err(err) => err.to_compile_error(),
+    }
+}
+
+pub fn unstable_items_macro(input: TokenStream) -> TokenStream {
+    let Some((args, items)) = split_at_semicolon(input.clone()) else {
+        return syn::Error::new_spanned(input, "expected `feature = \"...\"; <items>`")
+            .to_compile_error();
+    };
+    let attributes = match NestedMeta::parse_meta_list(args) {
+        Ok(attributes) => attributes,
+        Err(err) => return Error::from(err).write_errors(),
+    };
+    let unstable_attribute = match UnstableAttribute::from_list(&attributes) {
+        Ok(attributes) => attributes,
+        Err(err) => return err.write_errors(),
+    };
+    match parse_items.parse2(items) {
+        Ok(items) => unstable_attribute.expand_items(items),
+        Err(err) => err.to_compile_error(),
+    }
+}
+
+/// Parse a sequence of items.
+fn parse_items(input: ParseStream) -> syn::Result<Vec<Item>> {
+    let mut items = Vec::new();
+    while !input.is_empty() {
+        items.push(input.parse()?);
+    }
+    Ok(items)
+}
+
+/// Split the tokens at the first top-level `;`, which is not included in either part.
+fn split_at_semicolon(tokens: TokenStream) -> Option<(TokenStream, TokenStream)> {
+    let mut tokens = tokens.into_iter();
+    let mut before = TokenStream::new();
+    for token in tokens.by_ref() {
+        match token {
+            TokenTree::Punct(punct) if punct.as_char() == ';' => {
+                return Some((before, tokens.collect()));
+            }
+            token => before.extend([token]),
+        }
+    }
+    None
+}
+
+/// The branches of an `if_unstable!` invocation, i.e. `{ ... } else { ... }`.
+pub struct Branches {
+    then_branch: syn::Block,
+    else_branch: Option<syn::Block>,
+}
+
+impl Parse for Branches {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let then_branch = input.parse()?;
+        let else_branch = match input.parse::<Option<Token![else]>>()? {
+            Some(_) => Some(input.parse()?),
+            None => None,
+        };
+        Ok(Self {
+            then_branch,
+            else_branch,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default, FromMeta)]
+#[darling(and_then = Self::validate)]
 pub struct UnstableAttribute {
     /// The name of the feature that enables the unstable API.
     ///
@@ -44,10 +250,298 @@ pub struct UnstableAttribute {
     ///
     /// This will be included in the item's documentation.
     issue: Option<String>,
+
+    /// The reason why the API is still unstable, e.g. "naming not final".
+    ///
+    /// This will be included in the item's documentation.
+    reason: Option<String>,
+
+    /// An additional note about the unstable API.
+    ///
+    /// This will be appended to the item's documentation as its own paragraph.
+    note: Option<String>,
+
+    /// The version in which the item was introduced as unstable.
+    ///
+    /// This will be included in the item's documentation.
+    since: Option<Version>,
+
+    /// The maximum number of releases an item may stay unstable, counted from `since`.
+    ///
+    /// When the version of the crate being compiled is further than this from `since`, a warning
+    /// is emitted at compile time.
+    max_age: Option<u64>,
+
+    /// The visibility to use in place of the `pub` of each item when re-invoking an item-producing
+    /// macro without the feature enabled, e.g. `"pub(crate)"`.
+    ///
+    /// If not specified, the macro invocation is removed entirely when the feature is not enabled.
+    fallback_vis: Option<syn::Visibility>,
+
+    /// Whether a `pub use` re-export should be inlined and carry its own stability documentation.
+    ///
+    /// This has no effect on items other than `pub use`.
+    #[darling(default)]
+    inline: bool,
+
+    /// Whether the attribute is also applied to each public item in an inline module that does
+    /// not already carry a stability attribute.
+    ///
+    /// This has no effect on items other than `mod`.
+    #[darling(default)]
+    recursive: bool,
+
+    /// Whether the item also requires a nightly compiler, as detected by the build script helper.
+    ///
+    /// When the feature is enabled on a stable compiler, the item is silently left out instead of
+    /// failing to compile.
+    #[darling(default)]
+    nightly: bool,
+
+    /// Whether the item is included when generating documentation, even if the feature is not
+    /// enabled.
+    ///
+    /// This can be `true`, `false`, or the name of a cfg that must also be set when generating
+    /// documentation. Defaults to `true` unless the crate using the attribute is documented with
+    /// the `instability_disable_unstable_docs` cfg set.
+    docs: Option<CfgFlag>,
+
+    /// The cfg under which a `doc(cfg(...))` attribute is added to the item.
+    ///
+    /// This can be `true` (the default, using `docsrs`), `false` to not add the attribute, or the
+    /// name of a custom cfg.
+    doc_cfg: Option<CfgFlag>,
+
+    /// Further `unstable` attributes on the same item, which are merged into this one.
+    ///
+    /// The item is only available when all of the features are enabled.
+    #[darling(skip)]
+    stacked: Vec<UnstableAttribute>,
 }
 
 impl UnstableAttribute {
-    pub fn expand(&self, mut item: impl ItemLike + ToTokens + Clone) -> TokenStream {
+    fn validate(self) -> darling::Result<Self> {
+        if self.max_age.is_some() && self.since.is_none() {
+            return Err(Error::custom("`max_age` requires `since` to be specified"));
+        }
+        Ok(self)
+    }
+
+    /// Merge the other `unstable` attributes on the item into this one, removing them from the
+    /// item.
+    ///
+    /// An item that is also marked as `stable` is an error.
+    fn merge_stacked(&mut self, item: &mut Item) -> darling::Result<()> {
+        let Some(attrs) = item_attrs_mut(item) else {
+            return Ok(());
+        };
+        if let Some(attr) = find_instability_attr(attrs, "stable") {
+            return Err(
+                Error::custom("an item cannot be marked as both stable and unstable")
+                    .with_span(attr),
+            );
+        }
+        let (stacked, rest) = std::mem::take(attrs)
+            .into_iter()
+            .partition::<Vec<_>, _>(|attr| {
+                instability_attr_name(attr).as_deref() == Some("unstable")
+            });
+        *attrs = rest;
+        for attr in stacked {
+            let attributes = match &attr.meta {
+                syn::Meta::Path(_) => Vec::new(),
+                meta => NestedMeta::parse_meta_list(meta.require_list()?.tokens.clone())?,
+            };
+            self.stacked.push(Self::from_list(&attributes)?);
+        }
+        Ok(())
+    }
+
+    pub fn expand_item(&self, item: Item) -> TokenStream {
+        match item {
+            Item::Type(item_type) => self.expand(item_type),
+            Item::Enum(item_enum) => self.expand(item_enum),
+            Item::Struct(item_struct) => self.expand(item_struct),
+            Item::Union(item_union) => self.expand(item_union),
+            Item::Fn(item_fn) if item_fn.attrs.iter().any(is_proc_macro_entry) => {
+                self.expand_proc_macro(item_fn)
+            }
+            Item::Fn(item_fn) => self.expand_fn(item_fn),
+            Item::Mod(item_mod) => self.expand_mod(item_mod),
+            Item::Trait(item_trait) => self.expand(item_trait),
+            Item::Const(item_const) => self.expand_const_item(item_const),
+            Item::Static(item_static) => self.expand(item_static),
+            Item::Use(item_use) => self.expand_use(item_use),
+            Item::Impl(item_impl) if item_impl.trait_.is_none() => {
+                self.expand_inherent_impl(item_impl)
+            }
+            Item::Impl(item_impl) => self.expand_impl(item_impl),
+            Item::Macro(item_macro) if item_macro.ident.is_some() => {
+                self.expand_macro_rules(item_macro)
+            }
+            Item::Macro(item_macro) => self.expand_macro_invocation(item_macro),
+            Item::ForeignMod(item_foreign_mod) => self.expand_foreign_mod(item_foreign_mod),
+            // Foreign items (e.g. `pub fn foo();`) can't be parsed as regular items.
+            Item::Verbatim(tokens) => match syn::parse2::<ForeignItem>(tokens.clone()) {
+                Ok(foreign_item) => self.expand_foreign_item(foreign_item),
+                Err(_) => unsupported_item(tokens),
+            },
+            item => unsupported_item(item),
+        }
+    }
+
+    /// Expand each of the items, as if the attribute was applied to each of them.
+    ///
+    /// Any `unstable` attributes on the items are merged with this one.
+    pub fn expand_items(&self, items: Vec<Item>) -> TokenStream {
+        // The stale warning is emitted once for all items rather than for each of them.
+        let item_attribute = self.without_stale_warning();
+        let mut tokens = TokenStream::new();
+        for mut item in items {
+            let mut item_attribute = item_attribute.clone();
+            match item_attribute.merge_stacked(&mut item) {
+                Ok(()) => tokens.extend(item_attribute.expand_item(item)),
+                Err(err) => tokens.extend(err.write_errors()),
+            }
+        }
+        tokens.extend(self.stale_warning());
+        tokens
+    }
+
+    /// Expand a module, applying the attribute to its public child items if `recursive` is set.
+    ///
+    /// Each child item is only expanded once. Of the resulting items, the module's public copy
+    /// only keeps those that are available with the feature, and its restricted copy those that
+    /// are available without it, so that nested modules don't multiply the size of the output.
+    pub fn expand_mod(&self, mut item: syn::ItemMod) -> TokenStream {
+        if !self.recursive {
+            return self.expand(item);
+        }
+        let Some((brace, items)) = item.content.take() else {
+            return syn::Error::new_spanned(
+                &item,
+                "`recursive` can only be applied to inline modules",
+            )
+            .to_compile_error();
+        };
+        let items = self.expand_children(items);
+        if !item.is_public() {
+            // We only care about public items, but their children are expanded either way.
+            item.content = Some((brace, items));
+            return item.into_token_stream();
+        }
+
+        self.add_doc(&mut item);
+        let mut hidden_item = item.clone();
+        let (items, hidden_items) = self.split_gated(items);
+        item.content = Some((brace, items));
+        hidden_item.content = Some((brace, hidden_items));
+
+        let expanded = self.expand_copies(item, hidden_item);
+        let stale_warning = self.stale_warning();
+        quote! {
+            #expanded
+            #stale_warning
+        }
+    }
+
+    /// Apply the attribute to the public child items of a module that aren't annotated themselves.
+    fn expand_children(&self, items: Vec<Item>) -> Vec<Item> {
+        // The stale warning is emitted once for the module rather than for each child item.
+        let child_attribute = self.without_stale_warning();
+        let mut expanded_items = Vec::new();
+        for mut child in items {
+            let public = item_visibility(&child)
+                .is_some_and(|visibility| matches!(visibility, Visibility::Public(_)));
+            let annotated =
+                item_attrs_mut(&mut child).is_some_and(|attrs| has_stability_attr(attrs));
+            if !public || annotated {
+                expanded_items.push(child);
+                continue;
+            }
+            let expanded = child_attribute.expand_item(child);
+            match parse_items.parse2(expanded.clone()) {
+                Ok(items) => expanded_items.extend(items),
+                Err(_) => expanded_items.push(Item::Verbatim(expanded)),
+            }
+        }
+        expanded_items
+    }
+
+    /// Split expanded items into those for the copies of a module with and without the feature
+    /// enabled. Items that are not gated on either are included in both.
+    fn split_gated(&self, items: Vec<Item>) -> (Vec<Item>, Vec<Item>) {
+        let predicate = self.cfg_predicate();
+        let fallback = self.fallback_predicate();
+        let enabled_cfg: syn::Attribute = parse_quote! { #[cfg(#predicate)] };
+        let fallback_cfg: syn::Attribute = parse_quote! { #[cfg(#fallback)] };
+        let gated_on = |item: &mut Item, cfg: &syn::Attribute| {
+            item_attrs_mut(item).is_some_and(|attrs| attrs.first() == Some(cfg))
+        };
+
+        let mut enabled_items = Vec::new();
+        let mut fallback_items = Vec::new();
+        for mut item in items {
+            if gated_on(&mut item, &enabled_cfg) {
+                enabled_items.push(item);
+            } else if gated_on(&mut item, &fallback_cfg) {
+                fallback_items.push(item);
+            } else {
+                enabled_items.push(item.clone());
+                fallback_items.push(item);
+            }
+        }
+        (enabled_items, fallback_items)
+    }
+
+    pub fn expand(&self, item: impl ItemLike + ToTokens + Clone) -> TokenStream {
+        if !item.is_public() {
+            // We only care about public items.
+            return item.into_token_stream();
+        }
+
+        let expanded = self.expand_nested(item);
+        let stale_warning = self.stale_warning();
+        quote! {
+            #expanded
+            #stale_warning
+        }
+    }
+
+    /// Expand a function, which may also be a method within an `impl` block.
+    ///
+    /// The stale warning is emitted from within the function body, as items can't be added next
+    /// to a method.
+    fn expand_fn(&self, mut item: syn::ItemFn) -> TokenStream {
+        if !item.is_public() {
+            // We only care about public items.
+            return item.into_token_stream();
+        }
+        self.insert_stale_warning(&mut item);
+        self.without_stale_warning().expand(item)
+    }
+
+    /// Expand a constant, which may also be an associated constant within an `impl` block.
+    ///
+    /// The stale warning is emitted from within the constant's value, as items can't be added
+    /// next to an associated constant.
+    fn expand_const_item(&self, mut item: syn::ItemConst) -> TokenStream {
+        if !item.is_public() {
+            // We only care about public items.
+            return item.into_token_stream();
+        }
+        let stale_warning = self.stale_warning();
+        if !stale_warning.is_empty() {
+            let expr = &item.expr;
+            *item.expr = parse_quote! { { #stale_warning #expr } };
+        }
+        self.without_stale_warning().expand(item)
+    }
+
+    /// Expand an item that is nested within another item, such as an `extern` block.
+    ///
+    /// This does not emit the stale warning, as it can't be emitted next to a nested item.
+    fn expand_nested(&self, mut item: impl ItemLike + ToTokens + Clone) -> TokenStream {
         if !item.is_public() {
             // We only care about public items.
             return item.into_token_stream();
@@ -58,117 +552,745 @@ impl UnstableAttribute {
         self.expand_item_without_doc(item)
     }
 
-    pub fn expand_use(&self, item: impl ItemLike + ToTokens + Clone) -> TokenStream {
-        // We don't want to transform `pub use` items. Adding documentation has adverse effects.
-        // The reexported type can signal its own stability, the reexport itself can really only
-        // use the label that rustdoc renders.
+    pub fn expand_use(&self, mut item: syn::ItemUse) -> TokenStream {
         if !item.is_public() {
             // We only care about public items.
             return item.into_token_stream();
         }
 
-        self.expand_item_without_doc(item)
+        // By default we don't add documentation to `pub use` items, as it has adverse effects. The
+        // reexported type can signal its own stability, the reexport itself can really only use
+        // the label that rustdoc renders. When inlined, the reexport is documented as its own item
+        // and can carry the stability information.
+        if self.inline {
+            if let Err(err) = inline_reexport(&mut item) {
+                return err.to_compile_error();
+            }
+            self.add_doc(&mut item);
+        }
+
+        let expanded = self.expand_item_without_doc(item);
+        let stale_warning = self.stale_warning();
+        quote! {
+            #expanded
+            #stale_warning
+        }
     }
 
     fn expand_item_without_doc(&self, item: impl ItemLike + ToTokens + Clone) -> TokenStream {
-        let feature_flag = self.feature_flag();
+        self.expand_copies(item.clone(), item)
+    }
 
-        let mut hidden_item = item.clone();
+    /// Gate the item on the feature, and make the hidden item available within the crate
+    /// otherwise.
+    fn expand_copies<T: ItemLike + ToTokens>(&self, item: T, mut hidden_item: T) -> TokenStream {
         hidden_item.set_visibility(parse_quote! { pub(crate) });
+        // A restricted visibility does not prevent an exported symbol from being part of the
+        // crate's C ABI, so the symbol is only exported when the feature is enabled.
+        hidden_item
+            .attrs_mut()
+            .retain(|attr| !is_symbol_export(attr));
 
         let allows = item
             .allowed_lints()
             .into_iter()
             .map(|ident| quote! { #[allow(#ident)] });
 
-        #[cfg(not(instability_disable_unstable_docs))]
-        let (cfg_gate_unstable, cfg_gate_stable) = (
-            quote! { #[cfg(any(doc, feature = #feature_flag))] },
-            quote! { #[cfg(not(any(doc, feature = #feature_flag)))] },
-        );
-
-        #[cfg(instability_disable_unstable_docs)]
-        let (cfg_gate_unstable, cfg_gate_stable) = (
-            quote! { #[cfg(feature = #feature_flag)] },
-            quote! { #[cfg(not(feature = #feature_flag))] },
-        );
+        let predicate = self.cfg_predicate();
+        let fallback = self.fallback_predicate();
+        let doc_cfg = self.doc_cfg_attr();
 
         quote! {
-            #cfg_gate_unstable
-            #[cfg_attr(docsrs, doc(cfg(feature = #feature_flag)))]
+            #[cfg(#predicate)]
+            #doc_cfg
             #item
 
-            #cfg_gate_stable
+            #[cfg(#fallback)]
             #(#allows)*
             #hidden_item
         }
     }
 
-    pub fn expand_impl(&self, mut item: impl Stability + ToTokens) -> TokenStream {
-        let feature_flag = self.feature_flag();
-        self.add_doc(&mut item);
-
-        #[cfg(not(instability_disable_unstable_docs))]
-        let cfg_gate_unstable = quote! { #[cfg(any(doc, feature = #feature_flag))] };
-
-        #[cfg(instability_disable_unstable_docs)]
-        let cfg_gate_unstable = quote! { #[cfg(feature = #feature_flag)] };
-
+    /// Expand an `extern` block by applying the attribute to each of its public items.
+    pub fn expand_foreign_mod(&self, mut item: syn::ItemForeignMod) -> TokenStream {
+        item.items = item
+            .items
+            .into_iter()
+            .map(|foreign_item| ForeignItem::Verbatim(self.expand_foreign_item(foreign_item)))
+            .collect();
+        let stale_warning = self.stale_warning();
         quote! {
-            #cfg_gate_unstable
-            #[cfg_attr(docsrs, doc(cfg(feature = #feature_flag)))]
             #item
+            #stale_warning
         }
     }
 
-    fn add_doc(&self, item: &mut impl Stability) {
-        let feature_flag = self.feature_flag();
-        let doc = formatdoc! {"
-            # Stability
+    /// Expand a single item of an `extern` block.
+    pub fn expand_foreign_item(&self, item: ForeignItem) -> TokenStream {
+        match item {
+            ForeignItem::Fn(item_fn) => self.expand_nested(item_fn),
+            ForeignItem::Static(item_static) => self.expand_nested(item_static),
+            ForeignItem::Type(item_type) => self.expand_nested(item_type),
+            item => item.into_token_stream(),
+        }
+    }
 
-            **This API is marked as unstable** and is only available when the `{feature_flag}`
-            crate feature is enabled. This comes with no stability guarantees, and could be changed
-            or removed at any time."};
-        item.push_attr(parse_quote! { #[doc = #doc] });
+    /// Expand an inherent `impl` block by applying the attribute to each of its public items.
+    ///
+    /// Unlike trait implementations, inherent `impl` blocks are kept so that the crate itself can
+    /// still use the restricted items.
+    pub fn expand_inherent_impl(&self, mut item: syn::ItemImpl) -> TokenStream {
+        item.items = item
+            .items
+            .into_iter()
+            .map(|impl_item| ImplItem::Verbatim(self.expand_impl_item(impl_item)))
+            .collect();
+        let stale_warning = self.stale_warning();
+        quote! {
+            #item
+            #stale_warning
+        }
+    }
 
-        if let Some(issue) = &self.issue {
-            let doc = format!("The tracking issue is: `{}`.", issue);
-            item.push_attr(parse_quote! { #[doc = #doc] });
+    /// Expand a single item of an inherent `impl` block.
+    ///
+    /// Items with their own `stable` or `unstable` attribute are left for that attribute to expand.
+    fn expand_impl_item(&self, item: ImplItem) -> TokenStream {
+        match item {
+            ImplItem::Fn(item_fn) => self.expand_impl_member(item_fn),
+            ImplItem::Const(item_const) => self.expand_impl_member(item_const),
+            ImplItem::Type(item_type) => self.expand_impl_member(item_type),
+            item => item.into_token_stream(),
         }
     }
 
-    fn feature_flag(&self) -> String {
-        self.feature
-            .as_deref()
-            .map_or(String::from("unstable"), |name| format!("unstable-{name}"))
+    fn expand_impl_member(&self, item: impl ItemLike + ToTokens + Clone) -> TokenStream {
+        if has_stability_attr(item.attrs()) {
+            return item.into_token_stream();
+        }
+        self.expand_nested(item)
     }
-}
 
-#[cfg(all(test, not(instability_disable_unstable_docs)))]
-mod tests {
-    use pretty_assertions::assert_eq;
-    use quote::quote;
-    use syn::parse_quote;
+    /// Expand a `macro_rules!` definition.
+    ///
+    /// Exported macros ignore visibility, so instead of restricting the visibility, the
+    /// `#[macro_export]` attribute is dropped when the feature is not enabled. The macro is
+    /// usable within the crate either way, both textually and by path through a `pub(crate) use`.
+    pub fn expand_macro_rules(&self, mut item: syn::ItemMacro) -> TokenStream {
+        if !item.attrs.iter().any(is_macro_export) {
+            // We only care about exported macros.
+            return item.into_token_stream();
+        }
 
-    use super::*;
+        self.add_doc(&mut item);
 
-    #[test]
-    fn unstable_feature_flag_default() {
-        let unstable = UnstableAttribute::default();
-        assert_eq!(unstable.feature_flag(), "unstable");
-    }
+        let mut hidden_item = item.clone();
+        hidden_item.attrs.retain(|attr| !is_macro_export(attr));
+        let ident = &item.ident;
+        let reexport_mod = format_ident!("__instability_{}", ident.as_ref().unwrap());
 
-    #[test]
-    fn unstable_feature_flag_with_feature() {
-        let unstable = UnstableAttribute {
-            feature: Some("experimental".to_string()),
-            issue: None,
-        };
-        assert_eq!(unstable.feature_flag(), "unstable-experimental");
-    }
+        let predicate = self.cfg_predicate();
+        let fallback = self.fallback_predicate();
+        let doc_cfg = self.doc_cfg_attr();
+        let stale_warning = self.stale_warning();
 
-    #[test]
-    fn expand_non_public_item() {
+        // The macro is made usable by path within the crate whenever one of its copies exists. The
+        // glob import is shadowed by the exported macro at the crate root, where a named import
+        // would conflict.
+        quote! {
+            #[cfg(#predicate)]
+            #doc_cfg
+            #item
+
+            #[cfg(#fallback)]
+            #[allow(unused_macros)]
+            #hidden_item
+
+            #[cfg(any(#predicate, #fallback))]
+            #[doc(hidden)]
+            mod #reexport_mod {
+                #[allow(unused_imports)]
+                pub(crate) use #ident;
+            }
+
+            #[cfg(any(#predicate, #fallback))]
+            #[allow(unused_imports)]
+            pub(crate) use self::#reexport_mod::*;
+
+            #stale_warning
+        }
+    }
+
+    /// Expand an item-producing macro invocation, such as `bitflags! { ... }`.
+    ///
+    /// The produced items can't be rewritten, so the invocation is gated as a whole and the
+    /// documentation is inserted in front of each public item in the macro's input. Without the
+    /// feature, the macro is only invoked again if a `fallback_vis` was given, with the `pub` of
+    /// each of those items replaced by that visibility.
+    pub fn expand_macro_invocation(&self, mut item: syn::ItemMacro) -> TokenStream {
+        let doc_attrs = self.doc_attrs();
+        let mut hidden_item = item.clone();
+
+        let mut unstable_attrs = Vec::new();
+        if let Some(name) = self.doc_cfg_name() {
+            let features = self.feature_predicate();
+            unstable_attrs.push(parse_quote! { #[cfg_attr(#name, doc(cfg(#features)))] });
+        }
+        unstable_attrs.extend(doc_attrs.iter().cloned());
+        item.mac.tokens = match insert_macro_attrs(item.mac.tokens, &unstable_attrs) {
+            Ok(tokens) => tokens,
+            Err(err) => return err.to_compile_error(),
+        };
+
+        let predicate = self.cfg_predicate();
+        let fallback = self.fallback_predicate();
+        let stale_warning = self.stale_warning();
+
+        let hidden_item = match &self.fallback_vis {
+            Some(visibility) => {
+                let mut hidden_attrs = vec![parse_quote! { #[allow(dead_code)] }];
+                hidden_attrs.extend(doc_attrs);
+                let tokens = match insert_macro_attrs(hidden_item.mac.tokens, &hidden_attrs) {
+                    Ok(tokens) => tokens,
+                    Err(err) => return err.to_compile_error(),
+                };
+                hidden_item.mac.tokens = restrict_pub_tokens(tokens, visibility);
+                quote! {
+                    #[cfg(#fallback)]
+                    #hidden_item
+                }
+            }
+            None => TokenStream::new(),
+        };
+
+        quote! {
+            #[cfg(#predicate)]
+            #item
+
+            #hidden_item
+
+            #stale_warning
+        }
+    }
+
+    /// Expand a `#[proc_macro]`, `#[proc_macro_derive]` or `#[proc_macro_attribute]` function.
+    ///
+    /// Procedural macro entry points must be `pub`, so like `impl` blocks they are removed
+    /// entirely when the feature is not enabled.
+    pub fn expand_proc_macro(&self, item: syn::ItemFn) -> TokenStream {
+        self.expand_impl(item)
+    }
+
+    pub fn expand_impl(&self, mut item: impl Stability + ToTokens) -> TokenStream {
+        self.add_doc(&mut item);
+
+        let predicate = self.cfg_predicate();
+        let doc_cfg = self.doc_cfg_attr();
+        let stale_warning = self.stale_warning();
+
+        quote! {
+            #[cfg(#predicate)]
+            #doc_cfg
+            #item
+
+            #stale_warning
+        }
+    }
+
+    /// Fix the given generic parameters to their defaults unless the feature is enabled.
+    ///
+    /// Without the feature, the item is moved into a hidden module and replaced by a public type
+    /// alias that only has the stable generic parameters. The item keeps its name, so that e.g. its
+    /// `Debug` output is unchanged, and the visibilities of the item and its fields are adjusted so
+    /// that they are unchanged for the rest of the crate.
+    pub fn expand_params(
+        &self,
+        mut item: impl Generic + ToTokens + Clone,
+        params: &[syn::Ident],
+    ) -> TokenStream {
+        let mut defaults = Vec::new();
+        for param in params {
+            match generic_param_default(item.generics(), param) {
+                Ok(default) => defaults.push(default),
+                Err(err) => return err.to_compile_error(),
+            }
+        }
+
+        let feature_flag = self.feature_flag();
+        let names = format_list(params.iter().map(|param| format!("`{param}`")));
+        let (parameters, defaults_are) = match params {
+            [_] => ("parameter is", "it is always"),
+            _ => ("parameters are", "they are always"),
+        };
+        let values = format_list(defaults.iter().map(|default| format!("`{default}`")));
+        let doc = formatdoc! {"
+            # Stability
+
+            **The {names} generic {parameters} marked as unstable** and can only be specified when
+            the `{feature_flag}` crate feature is enabled. Otherwise {defaults_are} {values}. This
+            comes with no stability guarantees, and could be changed or removed at any time."};
+        item.push_attr(parse_quote! { #[doc = #doc] });
+        self.detail_doc_attrs()
+            .into_iter()
+            .for_each(|attr| item.push_attr(attr));
+
+        let ident = item.ident().clone();
+        let inner_mod = format_ident!("__instability_params_{}", ident);
+        let mut inner_item = item.clone();
+        inner_item
+            .attrs_mut()
+            .retain(|attr| !attr.path().is_ident("doc"));
+        for field in inner_item.fields_mut() {
+            field.vis = child_module_visibility(&field.vis);
+        }
+        inner_item.set_visibility(child_module_visibility(item.visibility()));
+
+        let docs = item
+            .attrs()
+            .iter()
+            .filter(|attr| attr.path().is_ident("doc"));
+        let visibility = item.visibility();
+        let alias_params = item
+            .generics()
+            .params
+            .iter()
+            .filter(|param| !is_one_of(param, params))
+            .map(strip_bounds);
+        let inner_args = item.generics().params.iter().map(|param| match param {
+            syn::GenericParam::Lifetime(lifetime) => lifetime.lifetime.to_token_stream(),
+            param => params
+                .iter()
+                .zip(&defaults)
+                .find(|(unstable, _)| param_ident(param) == *unstable)
+                .map_or_else(
+                    || param_ident(param).to_token_stream(),
+                    |(_, default)| default.clone(),
+                ),
+        });
+
+        // The type is available either way, so the alias is also used when the item requires a
+        // nightly compiler that isn't available.
+        let predicate = self.cfg_predicate();
+        let fallback = quote! { not(#predicate) };
+        let stale_warning = self.stale_warning();
+
+        quote! {
+            #[cfg(#predicate)]
+            #item
+
+            #[cfg(#fallback)]
+            #[doc(hidden)]
+            #[allow(non_snake_case)]
+            mod #inner_mod {
+                #[allow(unused_imports)]
+                use super::*;
+
+                #inner_item
+            }
+
+            #[cfg(#fallback)]
+            #(#docs)*
+            #visibility type #ident<#(#alias_params),*> = #inner_mod::#ident<#(#inner_args),*>;
+
+            #stale_warning
+        }
+    }
+
+    /// Whether the feature is enabled, as a `bool` that can be used in `const` contexts.
+    pub fn expand_is_enabled(&self) -> TokenStream {
+        let predicate = self.cfg_predicate();
+        quote! { cfg!(#predicate) }
+    }
+
+    /// Choose between the two branches depending on whether the feature is enabled.
+    ///
+    /// The branch that is not chosen is removed, so it may use items that are only available in
+    /// the other case.
+    pub fn expand_if_unstable(&self, branches: Branches) -> TokenStream {
+        let Branches {
+            then_branch,
+            else_branch,
+        } = branches;
+        let else_branch = else_branch.unwrap_or_else(|| parse_quote! { {} });
+        let value = syn::Ident::new("value", Span::mixed_site());
+        let predicate = self.cfg_predicate();
+        quote! {
+            {
+                #[cfg(#predicate)]
+                let #value = #then_branch;
+                #[cfg(not(#predicate))]
+                let #value = #else_branch;
+                #value
+            }
+        }
+    }
+
+    /// Make the function a `const fn` only when the feature is enabled.
+    ///
+    /// The function itself is always available, only its const-ness is unstable.
+    pub fn expand_const(&self, mut item: syn::ItemFn) -> TokenStream {
+        let feature_flag = self.feature_flag();
+        let doc = formatdoc! {"
+            # Stability
+
+            **Using this function in a `const` context is marked as unstable** and is only possible
+            when the `{feature_flag}` crate feature is enabled. Without it, this is not a `const fn`.
+            This comes with no stability guarantees, and could be changed or removed at any time."};
+        item.push_attr(parse_quote! { #[doc = #doc] });
+        self.detail_doc_attrs()
+            .into_iter()
+            .for_each(|attr| item.push_attr(attr));
+
+        // The function may be a method, so the stale warning is emitted from within its body.
+        self.insert_stale_warning(&mut item);
+
+        let mut non_const_item = item.clone();
+        non_const_item.sig.constness = None;
+        item.sig.constness = Some(Default::default());
+
+        // The function is available either way, so no `doc(cfg(...))` attribute is added.
+        let predicate = self.cfg_predicate();
+
+        quote! {
+            #[cfg(#predicate)]
+            #item
+
+            #[cfg(not(#predicate))]
+            #non_const_item
+        }
+    }
+
+    /// Derive the given traits only when the feature is enabled.
+    ///
+    /// The item itself is unchanged apart from a "Stability" section listing the gated impls.
+    pub fn expand_derive(
+        &self,
+        mut item: impl Stability + ToTokens,
+        derives: &[syn::Path],
+    ) -> TokenStream {
+        let feature_flag = self.feature_flag();
+        let traits = format_list(derives.iter().map(|path| {
+            let segments = path
+                .segments
+                .iter()
+                .map(|segment| segment.ident.to_string());
+            format!("`{}`", segments.collect::<Vec<_>>().join("::"))
+        }));
+        let doc = formatdoc! {"
+            # Stability
+
+            **The {traits} trait implementations are marked as unstable** and are only available
+            when the `{feature_flag}` crate feature is enabled. This comes with no stability
+            guarantees, and could be changed or removed at any time."};
+        item.push_attr(parse_quote! { #[doc = #doc] });
+        self.detail_doc_attrs()
+            .into_iter()
+            .for_each(|attr| item.push_attr(attr));
+
+        let predicate = self.cfg_predicate();
+        item.push_attr(parse_quote! { #[cfg_attr(#predicate, derive(#(#derives),*))] });
+
+        let stale_warning = self.stale_warning();
+        quote! {
+            #item
+            #stale_warning
+        }
+    }
+
+    fn add_doc(&self, item: &mut impl Stability) {
+        self.doc_attrs()
+            .into_iter()
+            .for_each(|attr| item.push_attr(attr));
+    }
+
+    fn doc_attrs(&self) -> Vec<syn::Attribute> {
+        let feature_flags = self.feature_flags();
+        let doc = if let [feature_flag] = &feature_flags[..] {
+            formatdoc! {"
+            # Stability
+
+            **This API is marked as unstable** and is only available when the `{feature_flag}`
+            crate feature is enabled. This comes with no stability guarantees, and could be changed
+            or removed at any time."}
+        } else {
+            let features = format_list(feature_flags.iter().map(|flag| format!("`{flag}`")));
+            formatdoc! {"
+            # Stability
+
+            **This API is marked as unstable** and is only available when the {features} crate
+            features are enabled. This comes with no stability guarantees, and could be changed or
+            removed at any time."}
+        };
+        let mut attrs = vec![parse_quote! { #[doc = #doc] }];
+        attrs.extend(self.detail_doc_attrs());
+        attrs
+    }
+
+    /// The documentation that follows the main paragraph of the "Stability" section.
+    fn detail_doc_attrs(&self) -> Vec<syn::Attribute> {
+        let mut attrs = Vec::new();
+
+        if self.nightly {
+            let doc =
+                "This API also requires a nightly compiler, and is not available on stable Rust \
+                even when the feature is enabled.";
+            attrs.push(parse_quote! { #[doc = #doc] });
+        }
+
+        if let Some(since) = &self.since {
+            let doc = format!("Unstable since {since}.");
+            attrs.push(parse_quote! { #[doc = #doc] });
+        }
+
+        if let Some(reason) = &self.reason {
+            let doc = format!(
+                "The reason for this API being unstable is: {}.",
+                reason.trim_end_matches('.')
+            );
+            attrs.push(parse_quote! { #[doc = #doc] });
+        }
+
+        attrs.extend(issue_and_note_doc_attrs(
+            self.issue.as_deref(),
+            self.note.as_deref(),
+        ));
+
+        for stacked in &self.stacked {
+            attrs.extend(stacked.detail_doc_attrs());
+        }
+        attrs
+    }
+
+    /// A compile-time warning if the item has been unstable for more than `max_age` releases.
+    ///
+    /// Proc macros cannot emit warnings on stable Rust, so this uses a deprecated constant instead.
+    fn stale_warning(&self) -> TokenStream {
+        let current = std::env::var("CARGO_PKG_VERSION")
+            .ok()
+            .and_then(|version| Version::parse(&version).ok());
+        let mut tokens = self.stale_warning_at(current.as_ref());
+        for stacked in &self.stacked {
+            tokens.extend(stacked.stale_warning_at(current.as_ref()));
+        }
+        tokens
+    }
+
+    /// Insert the stale warning as the first statement of the function body.
+    fn insert_stale_warning(&self, item: &mut syn::ItemFn) {
+        let stale_warning = self.stale_warning();
+        if !stale_warning.is_empty() {
+            let stmt = syn::Stmt::Item(Item::Verbatim(stale_warning));
+            item.block.stmts.insert(0, stmt);
+        }
+    }
+
+    /// A copy of this attribute that never emits the stale warning, for items that are expanded
+    /// alongside an item that already emits it, or that emit it themselves.
+    fn without_stale_warning(&self) -> Self {
+        Self {
+            max_age: None,
+            stacked: self
+                .stacked
+                .iter()
+                .map(Self::without_stale_warning)
+                .collect(),
+            ..self.clone()
+        }
+    }
+
+    fn stale_warning_at(&self, current: Option<&Version>) -> TokenStream {
+        let (Some(since), Some(max_age), Some(current)) = (&self.since, self.max_age, current)
+        else {
+            return TokenStream::new();
+        };
+        if since.releases_until(current) <= max_age {
+            return TokenStream::new();
+        }
+        let feature_flag = self.feature_flag();
+        let note = format!(
+            "`{feature_flag}` has been unstable since {since}, which is more than {max_age} \
+            releases before the current version {current}. Consider stabilizing or removing it."
+        );
+        quote! {
+            const _: () = {
+                #[deprecated(note = #note)]
+                const STALE_UNSTABLE_API: () = ();
+                STALE_UNSTABLE_API
+            };
+        }
+    }
+
+    /// The cfg predicate under which the unstable version of the item is available.
+    fn cfg_predicate(&self) -> TokenStream {
+        let predicate =
+            CfgFlag::gate_predicate_or_default(self.docs.as_ref(), self.feature_predicate());
+        if self.nightly {
+            quote! { all(instability_nightly, #predicate) }
+        } else {
+            predicate
+        }
+    }
+
+    /// The cfg predicate under which the restricted version of the item is available.
+    ///
+    /// Nightly-only items have no restricted version on other compilers, as they can't be compiled
+    /// there.
+    fn fallback_predicate(&self) -> TokenStream {
+        let predicate = self.cfg_predicate();
+        if self.nightly {
+            quote! { all(instability_nightly, not(#predicate)) }
+        } else {
+            quote! { not(#predicate) }
+        }
+    }
+
+    /// The `doc(cfg(...))` attribute that shows the required feature in the documentation.
+    fn doc_cfg_attr(&self) -> TokenStream {
+        self.doc_cfg_flag().doc_cfg_attr(&self.feature_predicate())
+    }
+
+    /// The cfg predicate that is true when all of the item's features are enabled.
+    fn feature_predicate(&self) -> TokenStream {
+        match &self.feature_flags()[..] {
+            [feature_flag] => quote! { feature = #feature_flag },
+            feature_flags => quote! { all(#(feature = #feature_flags),*) },
+        }
+    }
+
+    fn doc_cfg_name(&self) -> Option<syn::Ident> {
+        self.doc_cfg_flag().doc_cfg_name()
+    }
+
+    fn doc_cfg_flag(&self) -> &CfgFlag {
+        self.doc_cfg.as_ref().unwrap_or(&CfgFlag::Enabled)
+    }
+
+    fn feature_flag(&self) -> String {
+        self.feature
+            .as_deref()
+            .map_or(String::from("unstable"), |name| format!("unstable-{name}"))
+    }
+
+    /// The features of this attribute and any stacked attributes, without duplicates.
+    fn feature_flags(&self) -> Vec<String> {
+        let mut feature_flags = vec![self.feature_flag()];
+        for stacked in &self.stacked {
+            for feature_flag in stacked.feature_flags() {
+                if !feature_flags.contains(&feature_flag) {
+                    feature_flags.push(feature_flag);
+                }
+            }
+        }
+        feature_flags
+    }
+}
+
+/// The default of the given generic parameter, as a generic argument.
+fn generic_param_default(generics: &syn::Generics, param: &syn::Ident) -> syn::Result<TokenStream> {
+    let generic_param = generics
+        .params
+        .iter()
+        .filter(|generic_param| !matches!(generic_param, syn::GenericParam::Lifetime(_)))
+        .find(|generic_param| param_ident(generic_param) == param)
+        .ok_or_else(|| syn::Error::new_spanned(param, "no generic parameter with this name"))?;
+    match generic_param {
+        syn::GenericParam::Type(syn::TypeParam {
+            default: Some(default),
+            ..
+        }) => Ok(default.to_token_stream()),
+        syn::GenericParam::Const(syn::ConstParam {
+            default: Some(default),
+            ..
+        }) => Ok(quote! { { #default } }),
+        generic_param => Err(syn::Error::new_spanned(
+            generic_param,
+            "unstable generic parameters must have a default",
+        )),
+    }
+}
+
+fn param_ident(param: &syn::GenericParam) -> &syn::Ident {
+    match param {
+        syn::GenericParam::Lifetime(lifetime) => &lifetime.lifetime.ident,
+        syn::GenericParam::Type(type_param) => &type_param.ident,
+        syn::GenericParam::Const(const_param) => &const_param.ident,
+    }
+}
+
+fn is_one_of(param: &syn::GenericParam, params: &[syn::Ident]) -> bool {
+    !matches!(param, syn::GenericParam::Lifetime(_)) && params.contains(param_ident(param))
+}
+
+/// Remove the bounds of a generic parameter, as they are not enforced on type aliases.
+fn strip_bounds(param: &syn::GenericParam) -> syn::GenericParam {
+    let mut param = param.clone();
+    match &mut param {
+        syn::GenericParam::Lifetime(lifetime) => {
+            lifetime.colon_token = None;
+            lifetime.bounds.clear();
+        }
+        syn::GenericParam::Type(type_param) => {
+            type_param.colon_token = None;
+            type_param.bounds.clear();
+        }
+        syn::GenericParam::Const(_) => {}
+    }
+    param
+}
+
+/// The documentation of a tracking issue and an additional note, which is put in its own paragraph.
+pub fn issue_and_note_doc_attrs(issue: Option<&str>, note: Option<&str>) -> Vec<syn::Attribute> {
+    let mut attrs = Vec::new();
+
+    if let Some(issue) = issue {
+        let doc = format!("The tracking issue is: `{}`.", issue);
+        attrs.push(parse_quote! { #[doc = #doc] });
+    }
+
+    if let Some(note) = note {
+        // Consecutive doc attributes are joined with a single newline, so an empty line is needed
+        // to start a new paragraph.
+        attrs.push(parse_quote! { #[doc = ""] });
+        attrs.push(parse_quote! { #[doc = #note] });
+    }
+    attrs
+}
+
+/// Format a list of items as e.g. "a, b and c".
+fn format_list(items: impl IntoIterator<Item = String>) -> String {
+    let mut items = items.into_iter().collect::<Vec<_>>();
+    match items.pop() {
+        Some(last) if !items.is_empty() => format!("{} and {last}", items.join(", ")),
+        Some(last) => last,
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use quote::quote;
+    use syn::parse_quote;
+
+    use super::*;
+
+    #[test]
+    fn unstable_feature_flag_default() {
+        let unstable = UnstableAttribute::default();
+        assert_eq!(unstable.feature_flag(), "unstable");
+    }
+
+    #[test]
+    fn unstable_feature_flag_with_feature() {
+        let unstable = UnstableAttribute {
+            feature: Some("experimental".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(unstable.feature_flag(), "unstable-experimental");
+    }
+
+    #[test]
+    fn expand_non_public_item() {
         let item: syn::ItemStruct = parse_quote! {
             struct MyStruct;
         };
@@ -182,62 +1304,861 @@ mod tests {
     const ISSUE_DOC: &str = "The tracking issue is: `#123`.";
 
     #[test]
-    fn expand_with_feature() {
+    fn expand_with_feature() {
+        let item: syn::ItemType = parse_quote! { pub type Foo = Bar; };
+        let unstable = UnstableAttribute {
+            feature: Some("experimental".to_string()),
+            ..Default::default()
+        };
+        let tokens = unstable.expand(item);
+        let expected = quote! {
+            #[cfg(any(all(doc, not(instability_disable_unstable_docs)), feature = "unstable-experimental"))]
+            #[cfg_attr(docsrs, doc(cfg(feature = "unstable-experimental")))]
+            #[doc = #WITH_FEATURES_DOC]
+            pub type Foo = Bar;
+
+            #[cfg(not(any(all(doc, not(instability_disable_unstable_docs)), feature = "unstable-experimental")))]
+            #[allow(dead_code)]
+            #[doc = #WITH_FEATURES_DOC]
+            pub(crate) type Foo = Bar;
+        };
+        assert_eq!(tokens.to_string(), expected.to_string());
+    }
+
+    #[test]
+    fn expand_with_issue() {
+        let item: syn::ItemType = parse_quote! { pub type Foo = Bar; };
+        let unstable = UnstableAttribute {
+            issue: Some("#123".to_string()),
+            ..Default::default()
+        };
+        let tokens = unstable.expand(item);
+        let expected = quote! {
+            #[cfg(any(all(doc, not(instability_disable_unstable_docs)), feature = "unstable"))]
+            #[cfg_attr(docsrs, doc(cfg(feature = "unstable")))]
+            #[doc = #DEFAULT_DOC]
+            #[doc = #ISSUE_DOC]
+            pub type Foo = Bar;
+
+            #[cfg(not(any(all(doc, not(instability_disable_unstable_docs)), feature = "unstable")))]
+            #[allow(dead_code)]
+            #[doc = #DEFAULT_DOC]
+            #[doc = #ISSUE_DOC]
+            pub(crate) type Foo = Bar;
+        };
+        assert_eq!(tokens.to_string(), expected.to_string());
+    }
+
+    #[test]
+    fn expand_stacked() {
+        let mut item: Item = parse_quote! {
+            #[instability::unstable(feature = "bar", issue = "#456")]
+            pub type Foo = Bar;
+        };
+        let mut unstable = UnstableAttribute {
+            feature: Some("experimental".to_string()),
+            issue: Some("#123".to_string()),
+            ..Default::default()
+        };
+        unstable.merge_stacked(&mut item).unwrap();
+        let tokens = unstable.expand_item(item);
+        let doc = "# Stability\n\n**This API is marked as unstable** and is only available when the `unstable-experimental` and `unstable-bar` crate\nfeatures are enabled. This comes with no stability guarantees, and could be changed or\nremoved at any time.";
+        let stacked_issue_doc = "The tracking issue is: `#456`.";
+        let expected = quote! {
+            #[cfg(any(all(doc, not(instability_disable_unstable_docs)), all(feature = "unstable-experimental", feature = "unstable-bar")))]
+            #[cfg_attr(
+                docsrs,
+                doc(cfg(all(feature = "unstable-experimental", feature = "unstable-bar")))
+            )]
+            #[doc = #doc]
+            #[doc = #ISSUE_DOC]
+            #[doc = #stacked_issue_doc]
+            pub type Foo = Bar;
+
+            #[cfg(not(any(all(doc, not(instability_disable_unstable_docs)), all(feature = "unstable-experimental", feature = "unstable-bar"))))]
+            #[allow(dead_code)]
+            #[doc = #doc]
+            #[doc = #ISSUE_DOC]
+            #[doc = #stacked_issue_doc]
+            pub(crate) type Foo = Bar;
+        };
+        assert_eq!(tokens.to_string(), expected.to_string());
+    }
+
+    #[test]
+    fn stable_and_unstable_conflict() {
+        let mut item: Item = parse_quote! {
+            #[instability::stable(since = "v1.0.0")]
+            pub type Foo = Bar;
+        };
+        let mut unstable = UnstableAttribute::default();
+        assert!(unstable.merge_stacked(&mut item).is_err());
+    }
+
+    #[test]
+    fn expand_nightly() {
+        let item: syn::ItemType = parse_quote! { pub type Foo = Bar; };
+        let unstable = UnstableAttribute {
+            nightly: true,
+            ..Default::default()
+        };
+        let tokens = unstable.expand(item);
+        let nightly_doc = "This API also requires a nightly compiler, and is not available on stable Rust even when the feature is enabled.";
+        let expected = quote! {
+            #[cfg(all(instability_nightly, any(all(doc, not(instability_disable_unstable_docs)), feature = "unstable")))]
+            #[cfg_attr(docsrs, doc(cfg(feature = "unstable")))]
+            #[doc = #DEFAULT_DOC]
+            #[doc = #nightly_doc]
+            pub type Foo = Bar;
+
+            #[cfg(all(
+                instability_nightly,
+                not(all(instability_nightly, any(all(doc, not(instability_disable_unstable_docs)), feature = "unstable")))
+            ))]
+            #[allow(dead_code)]
+            #[doc = #DEFAULT_DOC]
+            #[doc = #nightly_doc]
+            pub(crate) type Foo = Bar;
+        };
+        assert_eq!(tokens.to_string(), expected.to_string());
+    }
+
+    #[test]
+    fn expand_is_enabled() {
+        let unstable = UnstableAttribute {
+            feature: Some("experimental".to_string()),
+            ..Default::default()
+        };
+        let tokens = unstable.expand_is_enabled();
+        let expected = quote! {
+            cfg!(any(all(doc, not(instability_disable_unstable_docs)), feature = "unstable-experimental"))
+        };
+        assert_eq!(tokens.to_string(), expected.to_string());
+    }
+
+    #[test]
+    fn expand_if_unstable() {
+        let tokens = if_unstable_macro(quote! {
+            feature = "experimental", docs = false; { 1 } else { 2 }
+        });
+        let expected = quote! {
+            {
+                #[cfg(feature = "unstable-experimental")]
+                let value = { 1 };
+                #[cfg(not(feature = "unstable-experimental"))]
+                let value = { 2 };
+                value
+            }
+        };
+        assert_eq!(tokens.to_string(), expected.to_string());
+    }
+
+    #[test]
+    fn expand_if_unstable_without_else() {
+        let tokens = if_unstable_macro(quote! {
+            docs = false; { foo(); }
+        });
+        let expected = quote! {
+            {
+                #[cfg(feature = "unstable")]
+                let value = { foo(); };
+                #[cfg(not(feature = "unstable"))]
+                let value = {};
+                value
+            }
+        };
+        assert_eq!(tokens.to_string(), expected.to_string());
+    }
+
+    #[test]
+    fn expand_items() {
+        let tokens = unstable_items_macro(quote! {
+            issue = "#123";
+            pub type Foo = Bar;
+            #[instability::unstable(feature = "experimental")]
+            pub type Baz = Bar;
+        });
+        let stacked_doc = "# Stability\n\n**This API is marked as unstable** and is only available when the `unstable` and `unstable-experimental` crate\nfeatures are enabled. This comes with no stability guarantees, and could be changed or\nremoved at any time.";
+        let expected = quote! {
+            #[cfg(any(all(doc, not(instability_disable_unstable_docs)), feature = "unstable"))]
+            #[cfg_attr(docsrs, doc(cfg(feature = "unstable")))]
+            #[doc = #DEFAULT_DOC]
+            #[doc = #ISSUE_DOC]
+            pub type Foo = Bar;
+
+            #[cfg(not(any(all(doc, not(instability_disable_unstable_docs)), feature = "unstable")))]
+            #[allow(dead_code)]
+            #[doc = #DEFAULT_DOC]
+            #[doc = #ISSUE_DOC]
+            pub(crate) type Foo = Bar;
+
+            #[cfg(any(all(doc, not(instability_disable_unstable_docs)), all(feature = "unstable", feature = "unstable-experimental")))]
+            #[cfg_attr(
+                docsrs,
+                doc(cfg(all(feature = "unstable", feature = "unstable-experimental")))
+            )]
+            #[doc = #stacked_doc]
+            #[doc = #ISSUE_DOC]
+            pub type Baz = Bar;
+
+            #[cfg(not(any(all(doc, not(instability_disable_unstable_docs)), all(feature = "unstable", feature = "unstable-experimental"))))]
+            #[allow(dead_code)]
+            #[doc = #stacked_doc]
+            #[doc = #ISSUE_DOC]
+            pub(crate) type Baz = Bar;
+        };
+        assert_eq!(tokens.to_string(), expected.to_string());
+    }
+
+    #[test]
+    fn expand_with_reason_and_note() {
+        let item: syn::ItemType = parse_quote! { pub type Foo = Bar; };
+        let unstable = UnstableAttribute {
+            issue: Some("#123".to_string()),
+            reason: Some("naming not final.".to_string()),
+            note: Some("Prefer `Baz` for now.".to_string()),
+            ..Default::default()
+        };
+        let tokens = unstable.expand(item);
+        let reason_doc = "The reason for this API being unstable is: naming not final.";
+        let note_doc = "Prefer `Baz` for now.";
+        let expected = quote! {
+            #[cfg(any(all(doc, not(instability_disable_unstable_docs)), feature = "unstable"))]
+            #[cfg_attr(docsrs, doc(cfg(feature = "unstable")))]
+            #[doc = #DEFAULT_DOC]
+            #[doc = #reason_doc]
+            #[doc = #ISSUE_DOC]
+            #[doc = ""]
+            #[doc = #note_doc]
+            pub type Foo = Bar;
+
+            #[cfg(not(any(all(doc, not(instability_disable_unstable_docs)), feature = "unstable")))]
+            #[allow(dead_code)]
+            #[doc = #DEFAULT_DOC]
+            #[doc = #reason_doc]
+            #[doc = #ISSUE_DOC]
+            #[doc = ""]
+            #[doc = #note_doc]
+            pub(crate) type Foo = Bar;
+        };
+        assert_eq!(tokens.to_string(), expected.to_string());
+    }
+
+    #[test]
+    fn expand_with_since() {
+        let item: syn::ItemType = parse_quote! { pub type Foo = Bar; };
+        let unstable = UnstableAttribute {
+            since: Some(Version::parse("v0.28.0").unwrap()),
+            ..Default::default()
+        };
+        let tokens = unstable.expand(item);
+        let since_doc = "Unstable since 0.28.0.";
+        let expected = quote! {
+            #[cfg(any(all(doc, not(instability_disable_unstable_docs)), feature = "unstable"))]
+            #[cfg_attr(docsrs, doc(cfg(feature = "unstable")))]
+            #[doc = #DEFAULT_DOC]
+            #[doc = #since_doc]
+            pub type Foo = Bar;
+
+            #[cfg(not(any(all(doc, not(instability_disable_unstable_docs)), feature = "unstable")))]
+            #[allow(dead_code)]
+            #[doc = #DEFAULT_DOC]
+            #[doc = #since_doc]
+            pub(crate) type Foo = Bar;
+        };
+        assert_eq!(tokens.to_string(), expected.to_string());
+    }
+
+    #[test]
+    fn stale_warning() {
+        let unstable = UnstableAttribute {
+            since: Some(Version::parse("0.20.0").unwrap()),
+            max_age: Some(5),
+            ..Default::default()
+        };
+        let fresh = Version::parse("0.25.0").unwrap();
+        assert!(unstable.stale_warning_at(Some(&fresh)).is_empty());
+
+        let stale = Version::parse("0.26.0").unwrap();
+        let note = "`unstable` has been unstable since 0.20.0, which is more than 5 releases before the current version 0.26.0. Consider stabilizing or removing it.";
+        let expected = quote! {
+            const _: () = {
+                #[deprecated(note = #note)]
+                const STALE_UNSTABLE_API: () = ();
+                STALE_UNSTABLE_API
+            };
+        };
+        assert_eq!(
+            unstable.stale_warning_at(Some(&stale)).to_string(),
+            expected.to_string()
+        );
+    }
+
+    #[test]
+    fn stale_warning_on_method() {
+        let tokens = unstable_macro(
+            quote! { since = "0.1.0", max_age = 1 },
+            quote! { pub fn bar(&self) {} },
+        );
+        assert!(tokens.to_string().contains("STALE_UNSTABLE_API"));
+        // The warning must be emitted within the method, as it can't be placed in an `impl` block.
+        let item_impl: syn::ItemImpl = parse_quote! { impl Foo { #tokens } };
+        assert_eq!(item_impl.items.len(), 2);
+        assert!(item_impl
+            .items
+            .iter()
+            .all(|item| matches!(item, ImplItem::Fn(_))));
+    }
+
+    #[test]
+    fn stale_warning_on_associated_const() {
+        let tokens = unstable_macro(
+            quote! { since = "0.1.0", max_age = 1 },
+            quote! { pub const BAR: i32 = 42; },
+        );
+        assert!(tokens.to_string().contains("STALE_UNSTABLE_API"));
+        let item_impl: syn::ItemImpl = parse_quote! { impl Foo { #tokens } };
+        assert_eq!(item_impl.items.len(), 2);
+        assert!(item_impl
+            .items
+            .iter()
+            .all(|item| matches!(item, ImplItem::Const(_))));
+    }
+
+    #[test]
+    fn max_age_requires_since() {
+        let attributes = NestedMeta::parse_meta_list(quote! { max_age = 5 }).unwrap();
+        assert!(UnstableAttribute::from_list(&attributes).is_err());
+    }
+
+    #[test]
+    fn expand_public_type() {
+        let item: syn::ItemType = parse_quote! { pub type Foo = Bar; };
+        let tokens = UnstableAttribute::default().expand(item);
+        let expected = quote! {
+            #[cfg(any(all(doc, not(instability_disable_unstable_docs)), feature = "unstable"))]
+            #[cfg_attr(docsrs, doc(cfg(feature = "unstable")))]
+            #[doc = #DEFAULT_DOC]
+            pub type Foo = Bar;
+
+            #[cfg(not(any(all(doc, not(instability_disable_unstable_docs)), feature = "unstable")))]
+            #[allow(dead_code)]
+            #[doc = #DEFAULT_DOC]
+            pub(crate) type Foo = Bar;
+        };
+        assert_eq!(tokens.to_string(), expected.to_string());
+    }
+
+    #[test]
+    fn expand_public_struct() {
+        let item: syn::ItemStruct = parse_quote! {
+            pub struct Foo {
+                pub field: i32,
+            }
+        };
+        let tokens = UnstableAttribute::default().expand(item);
+        let expected = quote! {
+            #[cfg(any(all(doc, not(instability_disable_unstable_docs)), feature = "unstable"))]
+            #[cfg_attr(docsrs, doc(cfg(feature = "unstable")))]
+            #[doc = #DEFAULT_DOC]
+            pub struct Foo {
+                pub field: i32,
+            }
+
+            #[cfg(not(any(all(doc, not(instability_disable_unstable_docs)), feature = "unstable")))]
+            #[allow(dead_code)]
+            #[doc = #DEFAULT_DOC]
+            pub(crate) struct Foo {
+                pub (crate) field: i32,
+            }
+        };
+        assert_eq!(tokens.to_string(), expected.to_string());
+    }
+
+    #[test]
+    fn expand_public_union() {
+        let item: syn::ItemUnion = parse_quote! {
+            pub union Foo {
+                pub a: u32,
+                b: f32,
+            }
+        };
+        let tokens = UnstableAttribute::default().expand(item);
+        let expected = quote! {
+            #[cfg(any(all(doc, not(instability_disable_unstable_docs)), feature = "unstable"))]
+            #[cfg_attr(docsrs, doc(cfg(feature = "unstable")))]
+            #[doc = #DEFAULT_DOC]
+            pub union Foo {
+                pub a: u32,
+                b: f32,
+            }
+
+            #[cfg(not(any(all(doc, not(instability_disable_unstable_docs)), feature = "unstable")))]
+            #[allow(dead_code)]
+            #[doc = #DEFAULT_DOC]
+            pub(crate) union Foo {
+                pub (crate) a: u32,
+                b: f32,
+            }
+        };
+        assert_eq!(tokens.to_string(), expected.to_string());
+    }
+
+    #[test]
+    fn expand_public_enum() {
+        let item: syn::ItemEnum = parse_quote! {
+            pub enum Foo {
+                A,
+                B,
+            }
+        };
+        let tokens = UnstableAttribute::default().expand(item);
+        let expected = quote! {
+            #[cfg(any(all(doc, not(instability_disable_unstable_docs)), feature = "unstable"))]
+            #[cfg_attr(docsrs, doc(cfg(feature = "unstable")))]
+            #[doc = #DEFAULT_DOC]
+            pub enum Foo {
+                A,
+                B,
+            }
+
+            #[cfg(not(any(all(doc, not(instability_disable_unstable_docs)), feature = "unstable")))]
+            #[allow(dead_code)]
+            #[doc = #DEFAULT_DOC]
+            pub(crate) enum Foo {
+                A,
+                B,
+            }
+        };
+        assert_eq!(tokens.to_string(), expected.to_string());
+    }
+
+    #[test]
+    fn expand_public_fn() {
+        let item: syn::ItemFn = parse_quote! {
+            pub fn foo() {}
+        };
+        let tokens = UnstableAttribute::default().expand(item);
+        let expected = quote! {
+            #[cfg(any(all(doc, not(instability_disable_unstable_docs)), feature = "unstable"))]
+            #[cfg_attr(docsrs, doc(cfg(feature = "unstable")))]
+            #[doc = #DEFAULT_DOC]
+            pub fn foo() {}
+
+            #[cfg(not(any(all(doc, not(instability_disable_unstable_docs)), feature = "unstable")))]
+            #[allow(dead_code)]
+            #[doc = #DEFAULT_DOC]
+            pub(crate) fn foo() {}
+        };
+        assert_eq!(tokens.to_string(), expected.to_string());
+    }
+
+    #[test]
+    fn expand_public_fn_with_symbol_export() {
+        let item: syn::ItemFn = parse_quote! {
+            #[no_mangle]
+            pub extern "C" fn foo() {}
+        };
+        let tokens = UnstableAttribute::default().expand(item);
+        let expected = quote! {
+            #[cfg(any(all(doc, not(instability_disable_unstable_docs)), feature = "unstable"))]
+            #[cfg_attr(docsrs, doc(cfg(feature = "unstable")))]
+            #[no_mangle]
+            #[doc = #DEFAULT_DOC]
+            pub extern "C" fn foo() {}
+
+            #[cfg(not(any(all(doc, not(instability_disable_unstable_docs)), feature = "unstable")))]
+            #[allow(dead_code)]
+            #[doc = #DEFAULT_DOC]
+            pub(crate) extern "C" fn foo() {}
+        };
+        assert_eq!(tokens.to_string(), expected.to_string());
+    }
+
+    #[test]
+    fn expand_public_static_with_unsafe_symbol_export() {
+        let item: syn::ItemStatic = parse_quote! {
+            #[unsafe(export_name = "foo")]
+            pub static FOO: i32 = 42;
+        };
+        let tokens = UnstableAttribute::default().expand(item);
+        let expected = quote! {
+            #[cfg(any(all(doc, not(instability_disable_unstable_docs)), feature = "unstable"))]
+            #[cfg_attr(docsrs, doc(cfg(feature = "unstable")))]
+            #[unsafe(export_name = "foo")]
+            #[doc = #DEFAULT_DOC]
+            pub static FOO: i32 = 42;
+
+            #[cfg(not(any(all(doc, not(instability_disable_unstable_docs)), feature = "unstable")))]
+            #[allow(dead_code)]
+            #[doc = #DEFAULT_DOC]
+            pub(crate) static FOO: i32 = 42;
+        };
+        assert_eq!(tokens.to_string(), expected.to_string());
+    }
+
+    #[test]
+    fn expand_proc_macro() {
+        let item: syn::ItemFn = parse_quote! {
+            #[proc_macro_derive(Foo)]
+            pub fn derive_foo(input: TokenStream) -> TokenStream {
+                input
+            }
+        };
+        let tokens = UnstableAttribute::default().expand_proc_macro(item);
+        let expected = quote! {
+            #[cfg(any(all(doc, not(instability_disable_unstable_docs)), feature = "unstable"))]
+            #[cfg_attr(docsrs, doc(cfg(feature = "unstable")))]
+            #[proc_macro_derive(Foo)]
+            #[doc = #DEFAULT_DOC]
+            pub fn derive_foo(input: TokenStream) -> TokenStream {
+                input
+            }
+        };
+        assert_eq!(tokens.to_string(), expected.to_string());
+    }
+
+    #[test]
+    fn expand_public_trait() {
+        let item: syn::ItemTrait = parse_quote! {
+            pub trait Foo {
+                fn bar(&self);
+            }
+        };
+        let tokens = UnstableAttribute::default().expand(item);
+        let expected = quote! {
+            #[cfg(any(all(doc, not(instability_disable_unstable_docs)), feature = "unstable"))]
+            #[cfg_attr(docsrs, doc(cfg(feature = "unstable")))]
+            #[doc = #DEFAULT_DOC]
+            pub trait Foo {
+                fn bar(&self);
+            }
+
+            #[cfg(not(any(all(doc, not(instability_disable_unstable_docs)), feature = "unstable")))]
+            #[allow(dead_code)]
+            #[doc = #DEFAULT_DOC]
+            pub(crate) trait Foo {
+                fn bar(&self);
+            }
+        };
+        assert_eq!(tokens.to_string(), expected.to_string());
+    }
+
+    #[test]
+    fn expand_public_const() {
+        let item: syn::ItemConst = parse_quote! {
+            pub const FOO: i32 = 42;
+        };
+        let tokens = UnstableAttribute::default().expand(item);
+        let expected = quote! {
+            #[cfg(any(all(doc, not(instability_disable_unstable_docs)), feature = "unstable"))]
+            #[cfg_attr(docsrs, doc(cfg(feature = "unstable")))]
+            #[doc = #DEFAULT_DOC]
+            pub const FOO: i32 = 42;
+
+            #[cfg(not(any(all(doc, not(instability_disable_unstable_docs)), feature = "unstable")))]
+            #[allow(dead_code)]
+            #[doc = #DEFAULT_DOC]
+            pub(crate) const FOO: i32 = 42;
+        };
+        assert_eq!(tokens.to_string(), expected.to_string());
+    }
+
+    #[test]
+    fn expand_public_static() {
+        let item: syn::ItemStatic = parse_quote! {
+            pub static FOO: i32 = 42;
+        };
+        let tokens = UnstableAttribute::default().expand(item);
+        let expected = quote! {
+            #[cfg(any(all(doc, not(instability_disable_unstable_docs)), feature = "unstable"))]
+            #[cfg_attr(docsrs, doc(cfg(feature = "unstable")))]
+            #[doc = #DEFAULT_DOC]
+            pub static FOO: i32 = 42;
+
+            #[cfg(not(any(all(doc, not(instability_disable_unstable_docs)), feature = "unstable")))]
+            #[allow(dead_code)]
+            #[doc = #DEFAULT_DOC]
+            pub(crate) static FOO: i32 = 42;
+        };
+        assert_eq!(tokens.to_string(), expected.to_string());
+    }
+
+    #[test]
+    fn expand_public_mod() {
+        let item: syn::ItemMod = parse_quote! {
+            pub mod foo {
+                pub fn bar() {}
+            }
+        };
+        let tokens = UnstableAttribute::default().expand(item);
+        let expected = quote! {
+            #[cfg(any(all(doc, not(instability_disable_unstable_docs)), feature = "unstable"))]
+            #[cfg_attr(docsrs, doc(cfg(feature = "unstable")))]
+            #[doc = #DEFAULT_DOC]
+            pub mod foo {
+                pub fn bar() {}
+            }
+
+            #[cfg(not(any(all(doc, not(instability_disable_unstable_docs)), feature = "unstable")))]
+            #[allow(dead_code)]
+            #[doc = #DEFAULT_DOC]
+            pub(crate) mod foo {
+                pub fn bar() {}
+            }
+        };
+        assert_eq!(tokens.to_string(), expected.to_string());
+    }
+
+    #[test]
+    fn expand_recursive_mod() {
+        let item: syn::ItemMod = parse_quote! {
+            pub mod foo {
+                pub fn bar() {}
+                fn baz() {}
+                #[instability::stable(since = "1.0.0")]
+                pub fn qux() {}
+            }
+        };
+        let unstable = UnstableAttribute {
+            recursive: true,
+            ..Default::default()
+        };
+        let tokens = unstable.expand_mod(item);
+        let expected = quote! {
+            #[cfg(any(all(doc, not(instability_disable_unstable_docs)), feature = "unstable"))]
+            #[cfg_attr(docsrs, doc(cfg(feature = "unstable")))]
+            #[doc = #DEFAULT_DOC]
+            pub mod foo {
+                #[cfg(any(all(doc, not(instability_disable_unstable_docs)), feature = "unstable"))]
+                #[cfg_attr(docsrs, doc(cfg(feature = "unstable")))]
+                #[doc = #DEFAULT_DOC]
+                pub fn bar() {}
+
+                fn baz() {}
+                #[instability::stable(since = "1.0.0")]
+                pub fn qux() {}
+            }
+
+            #[cfg(not(any(all(doc, not(instability_disable_unstable_docs)), feature = "unstable")))]
+            #[allow(dead_code)]
+            #[doc = #DEFAULT_DOC]
+            pub(crate) mod foo {
+                #[cfg(not(any(all(doc, not(instability_disable_unstable_docs)), feature = "unstable")))]
+                #[allow(dead_code)]
+                #[doc = #DEFAULT_DOC]
+                pub(crate) fn bar() {}
+
+                fn baz() {}
+                #[instability::stable(since = "1.0.0")]
+                pub fn qux() {}
+            }
+        };
+        assert_eq!(tokens.to_string(), expected.to_string());
+    }
+
+    #[test]
+    fn expand_recursive_mod_with_unstable_derive() {
+        let item: syn::ItemMod = parse_quote! {
+            pub mod foo {
+                #[instability::unstable_derive(feature = "b", Hash)]
+                pub struct Bar;
+            }
+        };
+        let unstable = UnstableAttribute {
+            recursive: true,
+            ..Default::default()
+        };
+        let tokens = unstable.expand_mod(item);
+        let expected = quote! {
+            #[cfg(any(all(doc, not(instability_disable_unstable_docs)), feature = "unstable"))]
+            #[cfg_attr(docsrs, doc(cfg(feature = "unstable")))]
+            #[doc = #DEFAULT_DOC]
+            pub mod foo {
+                #[cfg(any(all(doc, not(instability_disable_unstable_docs)), feature = "unstable"))]
+                #[cfg_attr(docsrs, doc(cfg(feature = "unstable")))]
+                #[instability::unstable_derive(feature = "b", Hash)]
+                #[doc = #DEFAULT_DOC]
+                pub struct Bar;
+            }
+
+            #[cfg(not(any(all(doc, not(instability_disable_unstable_docs)), feature = "unstable")))]
+            #[allow(dead_code)]
+            #[doc = #DEFAULT_DOC]
+            pub(crate) mod foo {
+                #[cfg(not(any(all(doc, not(instability_disable_unstable_docs)), feature = "unstable")))]
+                #[allow(dead_code)]
+                #[instability::unstable_derive(feature = "b", Hash)]
+                #[doc = #DEFAULT_DOC]
+                pub(crate) struct Bar;
+            }
+        };
+        assert_eq!(tokens.to_string(), expected.to_string());
+    }
+
+    #[test]
+    fn expand_nested_recursive_mods_once() {
+        let item: syn::ItemMod = parse_quote! {
+            pub mod a {
+                pub mod b {
+                    pub mod c {
+                        pub mod d {
+                            pub fn foo() {}
+                        }
+                    }
+                }
+            }
+        };
+        let unstable = UnstableAttribute {
+            recursive: true,
+            ..Default::default()
+        };
+        let tokens = unstable.expand_mod(item).to_string();
+        // One public and one restricted copy of the function, rather than one of each for every
+        // copy of each of the modules it is nested in.
+        assert_eq!(tokens.matches("fn foo").count(), 2);
+        assert_eq!(tokens.matches("mod d").count(), 2);
+    }
+
+    #[test]
+    fn expand_unsupported_items() {
+        let tokens = unstable_items_macro(quote! {
+            feature = "foo";
+            pub extern crate alloc;
+            pub trait Foo = Clone;
+        });
+        assert_eq!(tokens.to_string().matches("compile_error").count(), 2);
+    }
+
+    #[test]
+    fn expand_recursive_non_inline_mod() {
+        let item: syn::ItemMod = parse_quote! {
+            pub mod foo;
+        };
+        let unstable = UnstableAttribute {
+            recursive: true,
+            ..Default::default()
+        };
+        let tokens = unstable.expand_mod(item);
+        assert!(tokens.to_string().contains("compile_error"));
+    }
+
+    #[test]
+    fn expand_public_use() {
+        let item: syn::ItemUse = parse_quote! {
+            pub use crate::foo::bar;
+        };
+        let tokens = UnstableAttribute::default().expand_use(item);
+        let expected = quote! {
+            #[cfg(any(all(doc, not(instability_disable_unstable_docs)), feature = "unstable"))]
+            #[cfg_attr(docsrs, doc(cfg(feature = "unstable")))]
+            pub use crate::foo::bar;
+
+            #[cfg(not(any(all(doc, not(instability_disable_unstable_docs)), feature = "unstable")))]
+            #[allow(unused_imports)]
+            pub(crate) use crate::foo::bar;
+        };
+        assert_eq!(tokens.to_string(), expected.to_string());
+    }
+
+    #[test]
+    fn expand_public_use_inline() {
+        let item: syn::ItemUse = parse_quote! {
+            pub use crate::foo::bar;
+        };
+        let unstable = UnstableAttribute {
+            inline: true,
+            ..Default::default()
+        };
+        let tokens = unstable.expand_use(item);
+        let expected = quote! {
+            #[cfg(any(all(doc, not(instability_disable_unstable_docs)), feature = "unstable"))]
+            #[cfg_attr(docsrs, doc(cfg(feature = "unstable")))]
+            #[doc(inline)]
+            #[doc = #DEFAULT_DOC]
+            pub use crate::foo::bar;
+
+            #[cfg(not(any(all(doc, not(instability_disable_unstable_docs)), feature = "unstable")))]
+            #[allow(unused_imports)]
+            #[doc(inline)]
+            #[doc = #DEFAULT_DOC]
+            pub(crate) use crate::foo::bar;
+        };
+        assert_eq!(tokens.to_string(), expected.to_string());
+    }
+
+    #[test]
+    fn expand_public_use_inline_glob() {
+        let item: syn::ItemUse = parse_quote! {
+            pub use crate::foo::{bar, baz::*};
+        };
+        let unstable = UnstableAttribute {
+            inline: true,
+            ..Default::default()
+        };
+        let tokens = unstable.expand_use(item);
+        assert!(tokens.to_string().contains("compile_error"));
+    }
+
+    #[test]
+    fn expand_with_docs_disabled() {
         let item: syn::ItemType = parse_quote! { pub type Foo = Bar; };
         let unstable = UnstableAttribute {
-            feature: Some("experimental".to_string()),
-            issue: None,
+            docs: Some(CfgFlag::Disabled),
+            ..Default::default()
         };
         let tokens = unstable.expand(item);
         let expected = quote! {
-            #[cfg(any(doc, feature = "unstable-experimental"))]
-            #[cfg_attr(docsrs, doc(cfg(feature = "unstable-experimental")))]
-            #[doc = #WITH_FEATURES_DOC]
+            #[cfg(feature = "unstable")]
+            #[cfg_attr(docsrs, doc(cfg(feature = "unstable")))]
+            #[doc = #DEFAULT_DOC]
             pub type Foo = Bar;
 
-            #[cfg(not(any(doc, feature = "unstable-experimental")))]
+            #[cfg(not(feature = "unstable"))]
             #[allow(dead_code)]
-            #[doc = #WITH_FEATURES_DOC]
+            #[doc = #DEFAULT_DOC]
             pub(crate) type Foo = Bar;
         };
         assert_eq!(tokens.to_string(), expected.to_string());
     }
 
     #[test]
-    fn expand_with_issue() {
+    fn expand_with_docs_cfg() {
         let item: syn::ItemType = parse_quote! { pub type Foo = Bar; };
         let unstable = UnstableAttribute {
-            feature: None,
-            issue: Some("#123".to_string()),
+            docs: Some(CfgFlag::Cfg(parse_quote! { unstable_docs })),
+            ..Default::default()
         };
         let tokens = unstable.expand(item);
         let expected = quote! {
-            #[cfg(any(doc, feature = "unstable"))]
+            #[cfg(any(all(doc, unstable_docs), feature = "unstable"))]
             #[cfg_attr(docsrs, doc(cfg(feature = "unstable")))]
             #[doc = #DEFAULT_DOC]
-            #[doc = #ISSUE_DOC]
             pub type Foo = Bar;
 
-            #[cfg(not(any(doc, feature = "unstable")))]
+            #[cfg(not(any(all(doc, unstable_docs), feature = "unstable")))]
             #[allow(dead_code)]
             #[doc = #DEFAULT_DOC]
-            #[doc = #ISSUE_DOC]
             pub(crate) type Foo = Bar;
         };
         assert_eq!(tokens.to_string(), expected.to_string());
     }
 
     #[test]
-    fn expand_public_type() {
+    fn expand_with_custom_doc_cfg() {
         let item: syn::ItemType = parse_quote! { pub type Foo = Bar; };
-        let tokens = UnstableAttribute::default().expand(item);
+        let unstable = UnstableAttribute {
+            doc_cfg: Some(CfgFlag::Cfg(parse_quote! { ratatui_docs })),
+            ..Default::default()
+        };
+        let tokens = unstable.expand(item);
         let expected = quote! {
-            #[cfg(any(doc, feature = "unstable"))]
-            #[cfg_attr(docsrs, doc(cfg(feature = "unstable")))]
+            #[cfg(any(all(doc, not(instability_disable_unstable_docs)), feature = "unstable"))]
+            #[cfg_attr(ratatui_docs, doc(cfg(feature = "unstable")))]
             #[doc = #DEFAULT_DOC]
             pub type Foo = Bar;
 
-            #[cfg(not(any(doc, feature = "unstable")))]
+            #[cfg(not(any(all(doc, not(instability_disable_unstable_docs)), feature = "unstable")))]
             #[allow(dead_code)]
             #[doc = #DEFAULT_DOC]
             pub(crate) type Foo = Bar;
@@ -246,250 +2167,504 @@ mod tests {
     }
 
     #[test]
-    fn expand_public_struct() {
-        let item: syn::ItemStruct = parse_quote! {
-            pub struct Foo {
-                pub field: i32,
+    fn expand_impl_block_without_doc_cfg() {
+        let item: syn::ItemImpl = parse_quote! {
+            impl Default for crate::foo::Foo {}
+        };
+        let unstable = UnstableAttribute {
+            doc_cfg: Some(CfgFlag::Disabled),
+            ..Default::default()
+        };
+        let tokens = unstable.expand_impl(item);
+        let expected = quote! {
+            #[cfg(any(all(doc, not(instability_disable_unstable_docs)), feature = "unstable"))]
+            #[doc = #DEFAULT_DOC]
+            impl Default for crate::foo::Foo {}
+        };
+        assert_eq!(tokens.to_string(), expected.to_string());
+    }
+
+    #[test]
+    fn expand_exported_macro_rules() {
+        let item: syn::ItemMacro = parse_quote! {
+            #[macro_export]
+            macro_rules! foo {
+                () => {};
             }
         };
-        let tokens = UnstableAttribute::default().expand(item);
+        let tokens = UnstableAttribute::default().expand_macro_rules(item);
         let expected = quote! {
-            #[cfg(any(doc, feature = "unstable"))]
+            #[cfg(any(all(doc, not(instability_disable_unstable_docs)), feature = "unstable"))]
             #[cfg_attr(docsrs, doc(cfg(feature = "unstable")))]
+            #[macro_export]
             #[doc = #DEFAULT_DOC]
-            pub struct Foo {
-                pub field: i32,
+            macro_rules! foo {
+                () => {};
             }
 
-            #[cfg(not(any(doc, feature = "unstable")))]
-            #[allow(dead_code)]
+            #[cfg(not(any(all(doc, not(instability_disable_unstable_docs)), feature = "unstable")))]
+            #[allow(unused_macros)]
             #[doc = #DEFAULT_DOC]
-            pub(crate) struct Foo {
-                pub (crate) field: i32,
+            macro_rules! foo {
+                () => {};
+            }
+
+            #[cfg(any(
+                any(all(doc, not(instability_disable_unstable_docs)), feature = "unstable"),
+                not(any(all(doc, not(instability_disable_unstable_docs)), feature = "unstable"))
+            ))]
+            #[doc(hidden)]
+            mod __instability_foo {
+                #[allow(unused_imports)]
+                pub(crate) use foo;
             }
+
+            #[cfg(any(
+                any(all(doc, not(instability_disable_unstable_docs)), feature = "unstable"),
+                not(any(all(doc, not(instability_disable_unstable_docs)), feature = "unstable"))
+            ))]
+            #[allow(unused_imports)]
+            pub(crate) use self::__instability_foo::*;
         };
         assert_eq!(tokens.to_string(), expected.to_string());
     }
 
     #[test]
-    fn expand_public_enum() {
-        let item: syn::ItemEnum = parse_quote! {
-            pub enum Foo {
-                A,
-                B,
+    fn expand_nightly_macro_rules() {
+        let item: syn::ItemMacro = parse_quote! {
+            #[macro_export]
+            macro_rules! foo {
+                () => {};
             }
         };
-        let tokens = UnstableAttribute::default().expand(item);
-        let expected = quote! {
-            #[cfg(any(doc, feature = "unstable"))]
-            #[cfg_attr(docsrs, doc(cfg(feature = "unstable")))]
-            #[doc = #DEFAULT_DOC]
-            pub enum Foo {
-                A,
-                B,
+        let unstable = UnstableAttribute {
+            nightly: true,
+            ..Default::default()
+        };
+        let predicate = unstable.cfg_predicate();
+        let fallback = unstable.fallback_predicate();
+        let tokens = unstable.expand_macro_rules(item).to_string();
+        let reexport = quote! {
+            #[cfg(any(#predicate, #fallback))]
+            #[doc(hidden)]
+            mod __instability_foo
+        };
+        assert!(tokens.contains(&reexport.to_string()));
+    }
+
+    #[test]
+    fn expand_local_macro_rules() {
+        let item: syn::ItemMacro = parse_quote! {
+            macro_rules! foo {
+                () => {};
             }
+        };
+        let tokens = UnstableAttribute::default().expand_macro_rules(item.clone());
+        assert_eq!(tokens.to_string(), item.into_token_stream().to_string());
+    }
 
-            #[cfg(not(any(doc, feature = "unstable")))]
-            #[allow(dead_code)]
-            #[doc = #DEFAULT_DOC]
-            pub(crate) enum Foo {
-                A,
-                B,
+    #[test]
+    fn expand_macro_invocation() {
+        let item: syn::ItemMacro = parse_quote! {
+            bitflags! {
+                /// Flags.
+                pub struct Flags: u32 {
+                    const A = 1;
+                }
+            }
+        };
+        let tokens = UnstableAttribute::default().expand_macro_invocation(item);
+        let expected = quote! {
+            #[cfg(any(all(doc, not(instability_disable_unstable_docs)), feature = "unstable"))]
+            bitflags! {
+                /// Flags.
+                #[cfg_attr(docsrs, doc(cfg(feature = "unstable")))]
+                #[doc = #DEFAULT_DOC]
+                pub struct Flags: u32 {
+                    const A = 1;
+                }
             }
         };
         assert_eq!(tokens.to_string(), expected.to_string());
     }
 
     #[test]
-    fn expand_public_fn() {
-        let item: syn::ItemFn = parse_quote! {
-            pub fn foo() {}
+    fn expand_macro_invocation_with_fallback() {
+        let item: syn::ItemMacro = parse_quote! {
+            paste! {
+                pub struct Foo(pub(super) u8, pub u8);
+                /// Bar.
+                pub struct Bar {
+                    pub x: u8,
+                }
+            }
         };
-        let tokens = UnstableAttribute::default().expand(item);
+        let unstable = UnstableAttribute {
+            fallback_vis: Some(parse_quote! { pub(crate) }),
+            ..Default::default()
+        };
+        let tokens = unstable.expand_macro_invocation(item);
         let expected = quote! {
-            #[cfg(any(doc, feature = "unstable"))]
-            #[cfg_attr(docsrs, doc(cfg(feature = "unstable")))]
-            #[doc = #DEFAULT_DOC]
-            pub fn foo() {}
+            #[cfg(any(all(doc, not(instability_disable_unstable_docs)), feature = "unstable"))]
+            paste! {
+                #[cfg_attr(docsrs, doc(cfg(feature = "unstable")))]
+                #[doc = #DEFAULT_DOC]
+                pub struct Foo(pub(super) u8, pub u8);
+                /// Bar.
+                #[cfg_attr(docsrs, doc(cfg(feature = "unstable")))]
+                #[doc = #DEFAULT_DOC]
+                pub struct Bar {
+                    pub x: u8,
+                }
+            }
 
-            #[cfg(not(any(doc, feature = "unstable")))]
-            #[allow(dead_code)]
-            #[doc = #DEFAULT_DOC]
-            pub(crate) fn foo() {}
+            #[cfg(not(any(all(doc, not(instability_disable_unstable_docs)), feature = "unstable")))]
+            paste! {
+                #[allow(dead_code)]
+                #[doc = #DEFAULT_DOC]
+                pub(crate) struct Foo(pub(super) u8, pub u8);
+                /// Bar.
+                #[allow(dead_code)]
+                #[doc = #DEFAULT_DOC]
+                pub(crate) struct Bar {
+                    pub x: u8,
+                }
+            }
         };
         assert_eq!(tokens.to_string(), expected.to_string());
     }
 
     #[test]
-    fn expand_public_trait() {
-        let item: syn::ItemTrait = parse_quote! {
-            pub trait Foo {
-                fn bar(&self);
+    fn expand_macro_invocation_without_items() {
+        let item: syn::ItemMacro = parse_quote! {
+            newtype!(Meters, pub f64);
+        };
+        let tokens = UnstableAttribute::default().expand_macro_invocation(item);
+        assert!(tokens.to_string().contains("compile_error"));
+    }
+
+    #[test]
+    fn expand_foreign_mod() {
+        let item: syn::ItemForeignMod = parse_quote! {
+            extern "C" {
+                pub fn foo();
+                fn bar();
             }
         };
-        let tokens = UnstableAttribute::default().expand(item);
+        let tokens = UnstableAttribute::default().expand_foreign_mod(item);
         let expected = quote! {
-            #[cfg(any(doc, feature = "unstable"))]
-            #[cfg_attr(docsrs, doc(cfg(feature = "unstable")))]
-            #[doc = #DEFAULT_DOC]
-            pub trait Foo {
-                fn bar(&self);
-            }
+            extern "C" {
+                #[cfg(any(all(doc, not(instability_disable_unstable_docs)), feature = "unstable"))]
+                #[cfg_attr(docsrs, doc(cfg(feature = "unstable")))]
+                #[doc = #DEFAULT_DOC]
+                pub fn foo();
 
-            #[cfg(not(any(doc, feature = "unstable")))]
-            #[allow(dead_code)]
-            #[doc = #DEFAULT_DOC]
-            pub(crate) trait Foo {
-                fn bar(&self);
+                #[cfg(not(any(all(doc, not(instability_disable_unstable_docs)), feature = "unstable")))]
+                #[allow(dead_code)]
+                #[doc = #DEFAULT_DOC]
+                pub(crate) fn foo();
+
+                fn bar();
             }
         };
         assert_eq!(tokens.to_string(), expected.to_string());
     }
 
     #[test]
-    fn expand_public_const() {
-        let item: syn::ItemConst = parse_quote! {
-            pub const FOO: i32 = 42;
+    fn expand_foreign_static() {
+        let item: syn::ForeignItem = parse_quote! {
+            pub static FOO: i32;
         };
-        let tokens = UnstableAttribute::default().expand(item);
+        let tokens = UnstableAttribute::default().expand_foreign_item(item);
         let expected = quote! {
-            #[cfg(any(doc, feature = "unstable"))]
+            #[cfg(any(all(doc, not(instability_disable_unstable_docs)), feature = "unstable"))]
             #[cfg_attr(docsrs, doc(cfg(feature = "unstable")))]
             #[doc = #DEFAULT_DOC]
-            pub const FOO: i32 = 42;
+            pub static FOO: i32;
 
-            #[cfg(not(any(doc, feature = "unstable")))]
+            #[cfg(not(any(all(doc, not(instability_disable_unstable_docs)), feature = "unstable")))]
             #[allow(dead_code)]
             #[doc = #DEFAULT_DOC]
-            pub(crate) const FOO: i32 = 42;
+            pub(crate) static FOO: i32;
         };
         assert_eq!(tokens.to_string(), expected.to_string());
     }
 
     #[test]
-    fn expand_public_static() {
-        let item: syn::ItemStatic = parse_quote! {
-            pub static FOO: i32 = 42;
+    fn expand_derive() {
+        let item: syn::ItemStruct = parse_quote! {
+            pub struct Foo;
         };
-        let tokens = UnstableAttribute::default().expand(item);
+        let derives = [parse_quote! { serde::Serialize }, parse_quote! { Hash }];
+        let unstable = UnstableAttribute {
+            feature: Some("experimental".to_string()),
+            ..Default::default()
+        };
+        let tokens = unstable.expand_derive(item, &derives);
+        let doc = "# Stability\n\n**The `serde::Serialize` and `Hash` trait implementations are marked as unstable** and are only available\nwhen the `unstable-experimental` crate feature is enabled. This comes with no stability\nguarantees, and could be changed or removed at any time.";
         let expected = quote! {
-            #[cfg(any(doc, feature = "unstable"))]
-            #[cfg_attr(docsrs, doc(cfg(feature = "unstable")))]
-            #[doc = #DEFAULT_DOC]
-            pub static FOO: i32 = 42;
-
-            #[cfg(not(any(doc, feature = "unstable")))]
-            #[allow(dead_code)]
-            #[doc = #DEFAULT_DOC]
-            pub(crate) static FOO: i32 = 42;
+            #[doc = #doc]
+            #[cfg_attr(
+                any(all(doc, not(instability_disable_unstable_docs)), feature = "unstable-experimental"),
+                derive(serde::Serialize, Hash)
+            )]
+            pub struct Foo;
         };
         assert_eq!(tokens.to_string(), expected.to_string());
     }
 
     #[test]
-    fn expand_public_mod() {
-        let item: syn::ItemMod = parse_quote! {
-            pub mod foo {
-                pub fn bar() {}
+    fn expand_const() {
+        let item: syn::ItemFn = parse_quote! {
+            pub fn foo() -> i32 {
+                42
             }
         };
-        let tokens = UnstableAttribute::default().expand(item);
+        let unstable = UnstableAttribute {
+            feature: Some("const-foo".to_string()),
+            ..Default::default()
+        };
+        let tokens = unstable.expand_const(item);
+        let doc = "# Stability\n\n**Using this function in a `const` context is marked as unstable** and is only possible\nwhen the `unstable-const-foo` crate feature is enabled. Without it, this is not a `const fn`.\nThis comes with no stability guarantees, and could be changed or removed at any time.";
         let expected = quote! {
-            #[cfg(any(doc, feature = "unstable"))]
-            #[cfg_attr(docsrs, doc(cfg(feature = "unstable")))]
-            #[doc = #DEFAULT_DOC]
-            pub mod foo {
-                pub fn bar() {}
+            #[cfg(any(all(doc, not(instability_disable_unstable_docs)), feature = "unstable-const-foo"))]
+            #[doc = #doc]
+            pub const fn foo() -> i32 {
+                42
             }
 
-            #[cfg(not(any(doc, feature = "unstable")))]
-            #[allow(dead_code)]
-            #[doc = #DEFAULT_DOC]
-            pub(crate) mod foo {
-                pub fn bar() {}
+            #[cfg(not(any(all(doc, not(instability_disable_unstable_docs)), feature = "unstable-const-foo")))]
+            #[doc = #doc]
+            pub fn foo() -> i32 {
+                42
             }
         };
         assert_eq!(tokens.to_string(), expected.to_string());
     }
 
     #[test]
-    fn expand_public_use() {
-        let item: syn::ItemUse = parse_quote! {
-            pub use crate::foo::bar;
+    fn expand_params() {
+        let item: syn::ItemStruct = parse_quote! {
+            /// A vector.
+            #[derive(Debug)]
+            pub struct Vec<'a, T: Clone, A: Allocator = Global, const N: usize = 4> {
+                pub items: &'a [T],
+                allocator: A,
+            }
         };
-        let tokens = UnstableAttribute::default().expand_use(item);
+        let params = [parse_quote! { A }, parse_quote! { N }];
+        let tokens = UnstableAttribute::default().expand_params(item, &params);
+        let doc = "# Stability\n\n**The `A` and `N` generic parameters are marked as unstable** and can only be specified when\nthe `unstable` crate feature is enabled. Otherwise they are always `Global` and `{ 4 }`. This\ncomes with no stability guarantees, and could be changed or removed at any time.";
         let expected = quote! {
-            #[cfg(any(doc, feature = "unstable"))]
-            #[cfg_attr(docsrs, doc(cfg(feature = "unstable")))]
-            pub use crate::foo::bar;
+            #[cfg(any(all(doc, not(instability_disable_unstable_docs)), feature = "unstable"))]
+            /// A vector.
+            #[derive(Debug)]
+            #[doc = #doc]
+            pub struct Vec<'a, T: Clone, A: Allocator = Global, const N: usize = 4> {
+                pub items: &'a [T],
+                allocator: A,
+            }
 
-            #[cfg(not(any(doc, feature = "unstable")))]
-            #[allow(unused_imports)]
-            pub(crate) use crate::foo::bar;
+            #[cfg(not(any(all(doc, not(instability_disable_unstable_docs)), feature = "unstable")))]
+            #[doc(hidden)]
+            #[allow(non_snake_case)]
+            mod __instability_params_Vec {
+                #[allow(unused_imports)]
+                use super::*;
+
+                #[derive(Debug)]
+                pub struct Vec<'a, T: Clone, A: Allocator = Global, const N: usize = 4> {
+                    pub items: &'a [T],
+                    pub(super) allocator: A,
+                }
+            }
+
+            #[cfg(not(any(all(doc, not(instability_disable_unstable_docs)), feature = "unstable")))]
+            /// A vector.
+            #[doc = #doc]
+            pub type Vec<'a, T> = __instability_params_Vec::Vec<'a, T, Global, { 4 }>;
         };
         assert_eq!(tokens.to_string(), expected.to_string());
     }
 
     #[test]
-    fn expand_impl_block() {
-        let item: syn::ItemImpl = parse_quote! {
-            impl Default for crate::foo::Foo {}
+    fn child_module_visibilities() {
+        let child = |visibility: Visibility| {
+            child_module_visibility(&visibility)
+                .into_token_stream()
+                .to_string()
         };
-        let tokens = UnstableAttribute::default().expand_impl(item);
-        let expected = quote! {
-            #[cfg(any(doc, feature = "unstable"))]
-            #[cfg_attr(docsrs, doc(cfg(feature = "unstable")))]
-            #[doc = #DEFAULT_DOC]
-            impl Default for crate::foo::Foo {}
+        assert_eq!(child(parse_quote! {}), quote! { pub(super) }.to_string());
+        assert_eq!(child(parse_quote! { pub }), quote! { pub }.to_string());
+        assert_eq!(
+            child(parse_quote! { pub(crate) }),
+            quote! { pub(crate) }.to_string()
+        );
+        assert_eq!(
+            child(parse_quote! { pub(self) }),
+            quote! { pub(in super) }.to_string()
+        );
+        assert_eq!(
+            child(parse_quote! { pub(super) }),
+            quote! { pub(in super::super) }.to_string()
+        );
+        assert_eq!(
+            child(parse_quote! { pub(in super::foo) }),
+            quote! { pub(in super::super::foo) }.to_string()
+        );
+    }
+
+    #[test]
+    fn expand_nightly_params() {
+        let item: syn::ItemStruct = parse_quote! {
+            pub struct Foo<T = u8> {
+                pub value: T,
+            }
         };
-        assert_eq!(tokens.to_string(), expected.to_string());
+        let tokens = unstable_param_macro(quote! { T, nightly }, item.into_token_stream());
+        let predicate = UnstableAttribute {
+            nightly: true,
+            ..Default::default()
+        }
+        .cfg_predicate();
+        let fallback = quote! { #[cfg(not(#predicate))] }.to_string();
+        let alias = quote! { pub type Foo<> = __instability_params_Foo::Foo<u8>; }.to_string();
+        let tokens = tokens.to_string();
+        assert_eq!(tokens.matches(&fallback).count(), 2);
+        assert!(tokens.contains(&alias));
     }
-}
 
-#[cfg(all(test, instability_disable_unstable_docs))]
-mod tests {
-    use pretty_assertions::assert_eq;
-    use quote::quote;
-    use syn::parse_quote;
+    #[test]
+    fn split_flags_from_paths() {
+        let attributes = NestedMeta::parse_meta_list(quote! {
+            Hash, feature = "foo", nightly, inline, recursive, serde::Serialize
+        })
+        .unwrap();
+        let (paths, options) = split_paths(attributes);
+        let paths = paths.iter().map(|path| path.to_token_stream().to_string());
+        assert_eq!(
+            paths.collect::<Vec<_>>(),
+            ["Hash", &quote! { serde::Serialize }.to_string()]
+        );
+        assert_eq!(options.len(), 4);
+    }
 
-    use super::*;
+    #[test]
+    fn expand_params_without_default() {
+        let item: syn::ItemType = parse_quote! {
+            pub type Foo<T> = Bar<T>;
+        };
+        let tokens = UnstableAttribute::default().expand_params(item, &[parse_quote! { T }]);
+        assert!(tokens.to_string().contains("compile_error"));
+    }
 
-    const DEFAULT_DOC: &str = "# Stability\n\n**This API is marked as unstable** and is only available when the `unstable`\ncrate feature is enabled. This comes with no stability guarantees, and could be changed\nor removed at any time.";
+    #[test]
+    fn expand_params_tuple_and_unit_structs() {
+        for item in [
+            quote! { pub struct Foo<T = u8>(T); },
+            quote! { pub struct Foo<T = u8>; },
+        ] {
+            let tokens = unstable_param_macro(quote! { T }, item);
+            assert!(tokens.to_string().contains("compile_error"));
+        }
+    }
 
     #[test]
-    fn expand_public_fn() {
-        let item: syn::ItemFn = parse_quote! {
-            pub fn foo() {}
+    fn format_lists() {
+        let list = |items: &[&str]| format_list(items.iter().map(|item| item.to_string()));
+        assert_eq!(list(&[]), "");
+        assert_eq!(list(&["a"]), "a");
+        assert_eq!(list(&["a", "b"]), "a and b");
+        assert_eq!(list(&["a", "b", "c"]), "a, b and c");
+    }
+
+    #[test]
+    fn expand_inherent_impl_block() {
+        let item: syn::ItemImpl = parse_quote! {
+            impl Foo {
+                pub const BAR: i32 = 42;
+
+                pub fn bar(&self) {}
+
+                fn baz(&self) {}
+            }
         };
-        let tokens = UnstableAttribute::default().expand(item);
+        let tokens = UnstableAttribute::default().expand_inherent_impl(item);
         let expected = quote! {
-            #[cfg(feature = "unstable")]
-            #[cfg_attr(docsrs, doc(cfg(feature = "unstable")))]
-            #[doc = #DEFAULT_DOC]
-            pub fn foo() {}
+            impl Foo {
+                #[cfg(any(all(doc, not(instability_disable_unstable_docs)), feature = "unstable"))]
+                #[cfg_attr(docsrs, doc(cfg(feature = "unstable")))]
+                #[doc = #DEFAULT_DOC]
+                pub const BAR: i32 = 42;
 
-            #[cfg(not(feature = "unstable"))]
-            #[allow(dead_code)]
-            #[doc = #DEFAULT_DOC]
-            pub(crate) fn foo() {}
+                #[cfg(not(any(all(doc, not(instability_disable_unstable_docs)), feature = "unstable")))]
+                #[allow(dead_code)]
+                #[doc = #DEFAULT_DOC]
+                pub(crate) const BAR: i32 = 42;
+
+                #[cfg(any(all(doc, not(instability_disable_unstable_docs)), feature = "unstable"))]
+                #[cfg_attr(docsrs, doc(cfg(feature = "unstable")))]
+                #[doc = #DEFAULT_DOC]
+                pub fn bar(&self) {}
+
+                #[cfg(not(any(all(doc, not(instability_disable_unstable_docs)), feature = "unstable")))]
+                #[allow(dead_code)]
+                #[doc = #DEFAULT_DOC]
+                pub(crate) fn bar(&self) {}
+
+                fn baz(&self) {}
+            }
         };
         assert_eq!(tokens.to_string(), expected.to_string());
     }
 
     #[test]
-    fn expand_public_use_no_docs() {
-        let item: syn::ItemUse = parse_quote! {
-            pub use crate::foo::bar;
+    fn expand_inherent_impl_block_with_stable_item() {
+        let item: syn::ItemImpl = parse_quote! {
+            impl Foo {
+                #[instability::stable(since = "v1.0")]
+                pub fn bar(&self) {}
+            }
         };
-        let tokens = UnstableAttribute::default().expand_use(item);
+        let tokens = UnstableAttribute::default().expand_inherent_impl(item);
         let expected = quote! {
-            #[cfg(feature = "unstable")]
-            #[cfg_attr(docsrs, doc(cfg(feature = "unstable")))]
-            pub use crate::foo::bar;
+            impl Foo {
+                #[instability::stable(since = "v1.0")]
+                pub fn bar(&self) {}
+            }
+        };
+        assert_eq!(tokens.to_string(), expected.to_string());
+    }
 
-            #[cfg(not(feature = "unstable"))]
-            #[allow(unused_imports)]
-            pub(crate) use crate::foo::bar;
+    #[test]
+    fn expand_inherent_impl_block_with_unstable_item() {
+        let item: syn::ItemImpl = parse_quote! {
+            impl Foo {
+                #[instability::unstable(feature = "b")]
+                pub fn bar(&self) {}
+            }
+        };
+        let tokens = UnstableAttribute::default().expand_inherent_impl(item);
+        let expected = quote! {
+            impl Foo {
+                #[instability::unstable(feature = "b")]
+                pub fn bar(&self) {}
+            }
+        };
+        assert_eq!(tokens.to_string(), expected.to_string());
+    }
+
+    #[test]
+    fn expand_impl_block() {
+        let item: syn::ItemImpl = parse_quote! {
+            impl Default for crate::foo::Foo {}
+        };
+        let tokens = UnstableAttribute::default().expand_impl(item);
+        let expected = quote! {
+            #[cfg(any(all(doc, not(instability_disable_unstable_docs)), feature = "unstable"))]
+            #[cfg_attr(docsrs, doc(cfg(feature = "unstable")))]
+            #[doc = #DEFAULT_DOC]
+            impl Default for crate::foo::Foo {}
         };
         assert_eq!(tokens.to_string(), expected.to_string());
     }
@@ -0,0 +1,123 @@
+use std::fmt;
+
+use darling::{Error, FromMeta};
+
+/// A [Semantic Versioning](https://semver.org) version number.
+///
+/// A leading `v` is accepted and ignored, as are build metadata suffixes (`+build`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Version {
+    pub major: u64,
+    pub minor: u64,
+    pub patch: u64,
+    pub pre: Option<String>,
+}
+
+impl Version {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let invalid = || format!("`{value}` is not a valid semver version (e.g. `1.2.3`)");
+        let version = value.strip_prefix('v').unwrap_or(value);
+        let version = version
+            .split_once('+')
+            .map_or(version, |(version, _build)| version);
+        let (version, pre) = match version.split_once('-') {
+            Some((version, pre)) => (version, Some(pre)),
+            None => (version, None),
+        };
+        if pre.is_some_and(|pre| pre.split('.').any(str::is_empty)) {
+            return Err(invalid());
+        }
+        let parts = version
+            .split('.')
+            .map(parse_number)
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(invalid)?;
+        let [major, minor, patch] = parts[..] else {
+            return Err(invalid());
+        };
+        Ok(Self {
+            major,
+            minor,
+            patch,
+            pre: pre.map(String::from),
+        })
+    }
+
+    /// The number of releases between this version and `current`.
+    ///
+    /// This counts minor releases within the same major version. Any major release counts as
+    /// more releases than can be expressed.
+    pub fn releases_until(&self, current: &Version) -> u64 {
+        if current.major == self.major {
+            current.minor.saturating_sub(self.minor)
+        } else if current.major > self.major {
+            u64::MAX
+        } else {
+            0
+        }
+    }
+}
+
+fn parse_number(value: &str) -> Option<u64> {
+    let leading_zero = value.len() > 1 && value.starts_with('0');
+    if leading_zero || !value.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    value.parse().ok()
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)?;
+        if let Some(pre) = &self.pre {
+            write!(f, "-{pre}")?;
+        }
+        Ok(())
+    }
+}
+
+impl FromMeta for Version {
+    fn from_string(value: &str) -> darling::Result<Self> {
+        Self::parse(value).map_err(Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn parse_version() {
+        let version = Version::parse("v0.28.1-alpha.1+build").unwrap();
+        assert_eq!(version.to_string(), "0.28.1-alpha.1");
+    }
+
+    #[test]
+    fn parse_invalid_version() {
+        for value in [
+            "",
+            "1",
+            "1.2",
+            "1.2.3.4",
+            "1.02.3",
+            "1.x.3",
+            "1.2.3-",
+            "1.2.3-a..b",
+        ] {
+            assert!(Version::parse(value).is_err(), "{value}");
+        }
+    }
+
+    #[test]
+    fn releases_until() {
+        let since = Version::parse("0.20.0").unwrap();
+        assert_eq!(since.releases_until(&Version::parse("0.28.3").unwrap()), 8);
+        assert_eq!(since.releases_until(&Version::parse("0.19.0").unwrap()), 0);
+        assert_eq!(
+            since.releases_until(&Version::parse("1.0.0").unwrap()),
+            u64::MAX
+        );
+    }
+}